@@ -0,0 +1,134 @@
+//! Compares the spatial grid against checking every bullet against every unit,
+//! the way `absorb_bullets` used to, and every tower against every unit when aiming.
+//! Run with `cargo bench --bench spatial`.
+
+use std::time::{Duration, Instant};
+
+use bevy::prelude::*;
+
+#[path = "../src/spatial.rs"]
+mod spatial;
+
+use spatial::SpatialGrid;
+
+const UNIT_SIZE: f32 = 32.0;
+const BULLET_SIZE: f32 = 16.0;
+const FRAMES: u32 = 20;
+const TOWERS: usize = 50;
+const RANGE: f32 = 250.0;
+
+/// Cheap deterministic positions spread over the map, so runs are comparable.
+fn scatter(count: usize, seed: u32) -> Vec<Vec2> {
+    let mut state = seed;
+    let mut next = move || {
+        state ^= state << 13;
+        state ^= state >> 17;
+        state ^= state << 5;
+        (state % 1024) as f32 - 512.0
+    };
+    (0..count).map(|_| Vec2::new(next(), next())).collect()
+}
+
+fn touches(bullet: Vec2, unit: Vec2) -> bool {
+    let reach = (BULLET_SIZE + UNIT_SIZE) / 2.0;
+    (bullet - unit).abs().cmple(Vec2::splat(reach)).all()
+}
+
+fn brute_force(units: &[Vec2], bullets: &[Vec2]) -> usize {
+    bullets
+        .iter()
+        .map(|bullet| {
+            units
+                .iter()
+                .filter(|unit| touches(*bullet, **unit))
+                .count()
+        })
+        .sum()
+}
+
+fn grid(grid: &mut SpatialGrid, units: &[Vec2], bullets: &[Vec2]) -> usize {
+    grid.clear();
+    for (index, unit) in units.iter().enumerate() {
+        grid.insert(Entity::from_raw(index as u32), *unit, Vec2::splat(UNIT_SIZE));
+    }
+    let mut nearby = Vec::new();
+    bullets
+        .iter()
+        .map(|bullet| {
+            grid.query(*bullet, Vec2::splat(BULLET_SIZE), &mut nearby);
+            nearby
+                .iter()
+                .filter(|unit| touches(*bullet, units[unit.id() as usize]))
+                .count()
+        })
+        .sum()
+}
+
+fn brute_force_aim(units: &[Vec2], towers: &[Vec2]) -> usize {
+    towers
+        .iter()
+        .map(|tower| units.iter().filter(|unit| unit.distance(*tower) <= RANGE).count())
+        .sum()
+}
+
+/// Aiming reuses the grid built for collisions, so building it isn't timed here.
+fn grid_aim(grid: &SpatialGrid, units: &[Vec2], towers: &[Vec2]) -> usize {
+    let mut nearby = Vec::new();
+    towers
+        .iter()
+        .map(|tower| {
+            grid.query_radius(*tower, RANGE, &mut nearby);
+            nearby
+                .iter()
+                .filter(|unit| units[unit.id() as usize].distance(*tower) <= RANGE)
+                .count()
+        })
+        .sum()
+}
+
+fn time(mut run: impl FnMut() -> usize) -> (Duration, usize) {
+    let start = Instant::now();
+    let mut hits = 0;
+    for _ in 0..FRAMES {
+        hits = run();
+    }
+    (start.elapsed() / FRAMES, hits)
+}
+
+fn print_row(count: usize, others: usize, brute_time: Duration, grid_time: Duration) {
+    println!(
+        "{:>8} {:>8} {:>14?} {:>14?} {:>7.1}x",
+        count,
+        others,
+        brute_time,
+        grid_time,
+        brute_time.as_secs_f64() / grid_time.as_secs_f64()
+    );
+}
+
+fn main() {
+    println!("{:>8} {:>8} {:>14} {:>14} {:>8}", "units", "bullets", "brute force", "grid", "speedup");
+    for count in [100, 500, 1000, 2000, 5000] {
+        let units = scatter(count, 0x9e37_79b9);
+        let bullets = scatter(count, 0x85eb_ca6b);
+        let mut spatial = SpatialGrid::default();
+        let (brute_time, brute_hits) = time(|| brute_force(&units, &bullets));
+        let (grid_time, grid_hits) = time(|| grid(&mut spatial, &units, &bullets));
+        assert_eq!(brute_hits, grid_hits, "grid missed or invented hits");
+        print_row(count, count, brute_time, grid_time);
+    }
+    println!();
+    println!("{:>8} {:>8} {:>14} {:>14} {:>8}", "units", "towers", "brute force", "grid", "speedup");
+    let towers = scatter(TOWERS, 0xc2b2_ae35);
+    for count in [100, 500, 1000, 2000, 5000] {
+        let units = scatter(count, 0x9e37_79b9);
+        let mut spatial = SpatialGrid::default();
+        for (index, unit) in units.iter().enumerate() {
+            spatial.insert(Entity::from_raw(index as u32), *unit, Vec2::splat(UNIT_SIZE));
+        }
+        let (brute_time, brute_found) = time(|| brute_force_aim(&units, &towers));
+        let (grid_time, grid_found) = time(|| grid_aim(&spatial, &units, &towers));
+        assert_eq!(brute_found, grid_found, "grid missed or invented units in range");
+        print_row(count, TOWERS, brute_time, grid_time);
+    }
+}
@@ -0,0 +1,138 @@
+use std::marker::PhantomData;
+
+use bevy::{ecs::system::SystemParam, prelude::*};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    build::{
+        manage::{SellTower, SetTargeting, TowerType, UpgradeTower},
+        PlaceError, TowerBuilder,
+    },
+    components::{Gold, Targeting},
+    replay::Replay,
+    simulation::{SimulationAppExt, SimulationClock, SimulationSystem},
+    stages::{AutoSend, StartStage, UnitSpawner},
+    state::AppState,
+};
+
+/// Carries out what the player asked for at the start of the next tick, so that every game can be
+/// recorded and played back tick for tick.
+pub struct ActionsPlugin;
+
+impl Plugin for ActionsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PlayerActions>()
+            .init_resource::<Replay>()
+            .add_system_set(SystemSet::on_enter(AppState::Playing).with_system(reset_actions))
+            .add_simulation_system(SimulationSystem::Input, dispatch_actions);
+    }
+}
+
+/// Something the player did that changes the game. Towers are picked out by where they stand,
+/// since entities differ from one run to the next.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum PlayerAction {
+    PlaceTower { tower: String, position: Vec2 },
+    UpgradeTower { position: Vec2 },
+    SellTower { position: Vec2 },
+    SetTargeting { position: Vec2, targeting: Targeting },
+    StartStage,
+    ToggleAutoSend,
+    /// Debug key, spawns an enemy outside of any stage
+    SpawnUnit { enemy: String, position: Vec2 },
+    /// Debug key
+    AddGold(u32),
+}
+
+/// Actions waiting for the next tick
+#[derive(Debug, Default)]
+pub struct PlayerActions(Vec<PlayerAction>);
+
+impl PlayerActions {
+    pub fn push(&mut self, action: PlayerAction) {
+        self.0.push(action);
+    }
+    pub fn take(&mut self) -> Vec<PlayerAction> {
+        std::mem::take(&mut self.0)
+    }
+}
+
+fn reset_actions(mut actions: ResMut<PlayerActions>) {
+    actions.take();
+}
+
+/// This tick's actions: the player's, recorded if a recording is on, or the replay's
+#[derive(SystemParam)]
+struct ActionQueue<'w, 's> {
+    actions: ResMut<'w, PlayerActions>,
+    replay: ResMut<'w, Replay>,
+    clock: Res<'w, SimulationClock>,
+    #[system_param(ignore)]
+    marker: PhantomData<&'s ()>,
+}
+impl<'w, 's> ActionQueue<'w, 's> {
+    fn this_tick(&mut self) -> Vec<PlayerAction> {
+        let queued = self.actions.take();
+        self.replay.actions_for(self.clock.tick(), queued)
+    }
+}
+
+/// Sends stages and hands tower changes to the systems that make them
+#[derive(SystemParam)]
+struct Orders<'w, 's> {
+    towers: Query<'w, 's, (Entity, &'static Transform), With<TowerType>>,
+    start: EventWriter<'w, 's, StartStage>,
+    upgrades: EventWriter<'w, 's, UpgradeTower>,
+    sells: EventWriter<'w, 's, SellTower>,
+    targeting: EventWriter<'w, 's, SetTargeting>,
+}
+impl<'w, 's> Orders<'w, 's> {
+    fn tower_at(&self, position: Vec2) -> Option<Entity> {
+        self.towers
+            .iter()
+            .find(|(_, transform)| transform.translation.truncate() == position)
+            .map(|(entity, _)| entity)
+    }
+}
+
+fn dispatch_actions(
+    mut queue: ActionQueue,
+    mut builder: TowerBuilder,
+    mut orders: Orders,
+    mut spawner: UnitSpawner,
+    mut gold: ResMut<Gold>,
+    mut auto_send: ResMut<AutoSend>,
+) {
+    for action in queue.this_tick() {
+        match action {
+            PlayerAction::PlaceTower { tower, position } => {
+                match builder.place(&tower, position, &mut gold) {
+                    Ok(()) => {}
+                    Err(error @ PlaceError::UnknownTower(_)) => warn!("Can't place tower: {}", error),
+                    Err(error) => debug!("Didn't place {}: {}", tower, error),
+                }
+            }
+            PlayerAction::UpgradeTower { position } => {
+                if let Some(tower) = orders.tower_at(position) {
+                    orders.upgrades.send(UpgradeTower(tower));
+                }
+            }
+            PlayerAction::SellTower { position } => {
+                if let Some(tower) = orders.tower_at(position) {
+                    orders.sells.send(SellTower(tower));
+                }
+            }
+            PlayerAction::SetTargeting { position, targeting } => {
+                if let Some(tower) = orders.tower_at(position) {
+                    orders.targeting.send(SetTargeting(tower, targeting));
+                }
+            }
+            PlayerAction::StartStage => orders.start.send(StartStage),
+            PlayerAction::ToggleAutoSend => auto_send.0 = !auto_send.0,
+            PlayerAction::SpawnUnit { enemy, position } => {
+                spawner.spawn(&enemy, 0, position);
+            }
+            PlayerAction::AddGold(amount) => gold.0 += amount,
+        }
+    }
+}
@@ -1,6 +1,9 @@
 use bevy::prelude::*;
 
-use crate::components::MainCamera;
+use crate::{
+    components::MainCamera,
+    state::{AppState, DespawnOnExit},
+};
 
 pub struct BackgroundPlugin;
 
@@ -13,8 +16,7 @@ impl Plugin for BackgroundPlugin {
 #[derive(Debug, Clone, Component, Reflect)]
 pub struct Background;
 
-pub fn spawn_background(mut commands: Commands, asset_server: ResMut<AssetServer>) {
-    let texture: Handle<Image> = asset_server.load("debugmap.png");
+pub fn spawn_background(commands: &mut Commands, texture: Handle<Image>) {
     commands.spawn()
     .insert_bundle(SpriteBundle {
         sprite: Sprite {
@@ -25,7 +27,8 @@ pub fn spawn_background(mut commands: Commands, asset_server: ResMut<AssetServer
         transform: Transform::from_translation(Vec3::new(0.0, 0.0, 0.0)),
         ..Default::default()
     })
-    .insert(Background);
+    .insert(Background)
+    .insert(DespawnOnExit(AppState::Playing));
 }
 
 fn resize_camera(
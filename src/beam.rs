@@ -0,0 +1,214 @@
+use bevy::prelude::*;
+
+use crate::{
+    components::{
+        choose_target, credit, mitigated, AiUnit, Aim, Armor, DamageDealt, DamageType, Health,
+        Targeting, Targets,
+    },
+    effects::{StatusEffect, StatusEffects},
+    pathfinding::NavPaths,
+    simulation::{SimulationAppExt, SimulationClock, SimulationSystem},
+    spatial::SpatialGrid,
+};
+
+pub struct BeamPlugin;
+
+impl Plugin for BeamPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_simulation_system(SimulationSystem::Aim, aim_beams)
+            .add_simulation_system(SimulationSystem::Beam, damage_beams)
+            .add_system(draw_beams);
+    }
+}
+
+/// Seconds between reapplying a beam's effects to the units it stays on
+const BEAM_EFFECT_INTERVAL: f32 = 1.0;
+
+#[derive(Clone, Debug, Component)]
+/// Continuous attack that damages its target every frame it's in range,
+/// jumping on to nearby units if it chains.
+pub struct Beam {
+    /// Damage per second to each unit in the beam
+    pub dps: f32,
+    pub damage_type: DamageType,
+    /// Extra units the beam jumps to after its target
+    pub chain: usize,
+    /// Furthest the beam can jump between units
+    pub chain_range: f32,
+    /// Applied to units as they enter the beam, then every `BEAM_EFFECT_INTERVAL` they stay in it
+    pub effects: Vec<StatusEffect>,
+    pub color: Color,
+    pub width: f32,
+    /// Units currently in the beam, starting with the target
+    links: Vec<Entity>,
+    /// Units that joined the beam this tick
+    fresh: Vec<Entity>,
+    /// Seconds since the effects were last applied to every link
+    since_effects: f32,
+    /// Sprites drawing each link, children of the tower
+    segments: Vec<Entity>,
+}
+impl Beam {
+    pub fn new(dps: f32, damage_type: DamageType) -> Self {
+        Self {
+            dps,
+            damage_type,
+            chain: 0,
+            chain_range: 150.0,
+            effects: Vec::new(),
+            color: Color::rgb(1.0, 0.2, 0.2),
+            width: 6.0,
+            links: Vec::new(),
+            fresh: Vec::new(),
+            since_effects: 0.0,
+            segments: Vec::new(),
+        }
+    }
+    pub fn with_chain(mut self, chain: usize, chain_range: f32) -> Self {
+        self.chain = chain;
+        self.chain_range = chain_range;
+        self
+    }
+    pub fn with_effects(mut self, effects: Vec<StatusEffect>) -> Self {
+        self.effects = effects;
+        self
+    }
+    pub fn with_looks(mut self, color: Color, width: f32) -> Self {
+        self.color = color;
+        self.width = width;
+        self
+    }
+}
+
+#[derive(Clone, Debug, Component)]
+/// Sprite for one link of a beam
+struct BeamSegment;
+
+fn aim_beams(
+    mut beams: Query<(&mut Beam, &Transform, &Aim, Option<&Targeting>)>,
+    targets: Targets,
+    grid: Res<SpatialGrid>,
+    paths: Res<NavPaths>,
+) {
+    let mut nearby = Vec::new();
+    for (mut beam, transform, aim, targeting) in beams.iter_mut() {
+        let targeting = targeting.copied().unwrap_or_default();
+        let mut links = Vec::new();
+        if let Some((entity, target, _)) =
+            choose_target(&targets, &grid, &paths, transform.translation, aim, targeting)
+        {
+            links.push(entity);
+            let mut last = target.translation;
+            for _ in 0..beam.chain {
+                grid.query_radius(last.truncate(), beam.chain_range, &mut nearby);
+                let next = nearby
+                    .iter()
+                    .filter_map(|entity| targets.get(*entity).ok())
+                    .filter(|(entity, ..)| !links.contains(entity))
+                    .map(|(entity, target, ..)| (entity, target.translation))
+                    .filter(|(_, position)| position.distance(last) <= beam.chain_range)
+                    .reduce(|x, y| {
+                        if x.1.distance_squared(last) > y.1.distance_squared(last) {
+                            y
+                        } else {
+                            x
+                        }
+                    });
+                match next {
+                    Some((entity, position)) => {
+                        links.push(entity);
+                        last = position;
+                    }
+                    None => break,
+                }
+            }
+        }
+        beam.fresh = links.iter().filter(|link| !beam.links.contains(link)).copied().collect();
+        beam.links = links;
+    }
+}
+
+/// Units as beams see them
+type BeamTargets<'w, 's> = Query<
+    'w,
+    's,
+    (&'static mut Health, Option<&'static Armor>, Option<&'static mut StatusEffects>),
+    With<AiUnit>,
+>;
+
+fn damage_beams(
+    mut beams: Query<(Entity, &mut Beam)>,
+    mut units: BeamTargets,
+    mut dealt: Query<&mut DamageDealt>,
+    clock: Res<SimulationClock>,
+) {
+    for (tower, mut beam) in beams.iter_mut() {
+        let damage = beam.dps * clock.delta_seconds();
+        beam.since_effects += clock.delta_seconds();
+        let reapply = beam.since_effects >= BEAM_EFFECT_INTERVAL;
+        if reapply {
+            beam.since_effects -= BEAM_EFFECT_INTERVAL;
+        }
+        for link in beam.links.iter() {
+            if let Ok((mut health, armor, status)) = units.get_mut(*link) {
+                let lost = health.damage(mitigated(damage, beam.damage_type, armor));
+                credit(&mut dealt, Some(tower), lost);
+                if let Some(mut status) = status {
+                    if reapply || beam.fresh.contains(link) {
+                        status.apply_all(&beam.effects);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Stretches a sprite between each pair of units in the beam, making more as the chain grows
+/// and hiding the ones that aren't needed.
+fn draw_beams(
+    mut commands: Commands,
+    mut beams: Query<(Entity, &mut Beam, &Transform), Without<BeamSegment>>,
+    units: Query<&Transform, (With<AiUnit>, Without<BeamSegment>)>,
+    mut segments: Query<(&mut Transform, &mut Sprite, &mut Visibility), With<BeamSegment>>,
+) {
+    for (tower, mut beam, transform) in beams.iter_mut() {
+        let origin = transform.translation;
+        let mut points = vec![origin];
+        points.extend(beam.links.iter().filter_map(|link| units.get(*link).ok()).map(|unit| unit.translation));
+        let needed = points.len() - 1;
+        while beam.segments.len() < needed {
+            let segment = commands
+                .spawn_bundle(SpriteBundle {
+                    visibility: Visibility { is_visible: false },
+                    ..Default::default()
+                })
+                .insert(BeamSegment)
+                .id();
+            commands.entity(tower).add_child(segment);
+            beam.segments.push(segment);
+        }
+        for (index, segment) in beam.segments.iter().enumerate() {
+            let (mut segment_transform, mut sprite, mut visibility) = match segments.get_mut(*segment) {
+                Ok(segment) => segment,
+                // Spawned this frame
+                Err(_) => continue,
+            };
+            visibility.is_visible = index < needed;
+            if index >= needed {
+                continue;
+            }
+            let (start, end) = (points[index], points[index + 1]);
+            let offset = (end - start).truncate();
+            if offset == Vec2::ZERO {
+                visibility.is_visible = false;
+                continue;
+            }
+            // Segments are children of the tower, so positions are relative to it
+            let middle = (start + end) / 2.0 - origin;
+            *segment_transform = Transform::from_translation(middle.truncate().extend(1.0))
+                .with_rotation(Quat::from_rotation_arc(Vec3::Y, offset.normalize().extend(0.0)));
+            sprite.color = beam.color;
+            sprite.custom_size = Some(Vec2::new(beam.width, offset.length()));
+        }
+    }
+}
@@ -0,0 +1,230 @@
+use bevy::{app::Events, prelude::*};
+
+use crate::{
+    beam::Beam,
+    components::{
+        Aim, BulletGenerator, CursorOverUi, CursorPosition, Gold, StructureRect, Targeting,
+    },
+    simulation::{SimulationAppExt, SimulationSystem},
+    state::{AppState, DespawnOnExit},
+};
+
+use super::{
+    towerdata::{TowerDefinitions, TowerDefinitionsHandle, UpgradeTier},
+    BuildSystem,
+};
+
+/// Shortest cooldown upgrades can bring a tower down to
+const MIN_COOLDOWN: f32 = 0.05;
+
+#[derive(Clone, Debug, Component)]
+/// Id of the tower definition a placed tower was built from
+pub struct TowerType(pub String);
+
+#[derive(Clone, Debug, Component, Default)]
+/// How many upgrade tiers have been bought for a placed tower
+pub struct UpgradeLevel(pub usize);
+
+#[derive(Debug, Default)]
+/// Placed tower the player has clicked on
+pub struct SelectedTower(pub Option<Entity>);
+
+/// Request to buy the next upgrade tier of a tower
+pub struct UpgradeTower(pub Entity);
+
+/// Request to remove a tower and refund part of its cost
+pub struct SellTower(pub Entity);
+
+/// Request to change which units a tower shoots at
+pub struct SetTargeting(pub Entity, pub Targeting);
+
+#[derive(Component)]
+/// Circle showing the range of the selected tower
+pub struct RangeIndicator;
+
+/// Selecting, upgrading, selling and retargeting towers that have already been placed
+pub struct ManagePlugin;
+
+impl Plugin for ManagePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SelectedTower>()
+            .add_event::<UpgradeTower>()
+            .add_event::<SellTower>()
+            .add_event::<SetTargeting>()
+            .add_system_set(
+                SystemSet::on_enter(AppState::Playing)
+                    .with_system(spawn_range_indicator)
+                    .with_system(reset_selection),
+            )
+            .add_system_set(
+                SystemSet::on_update(AppState::Playing)
+                    .with_system(select_tower.after(BuildSystem::Build))
+                    .with_system(update_range_indicator),
+            )
+            .add_simulation_system(SimulationSystem::Orders, upgrade_towers)
+            .add_simulation_system(SimulationSystem::Orders, sell_towers)
+            .add_simulation_system(SimulationSystem::Orders, set_targeting);
+    }
+}
+
+/// Left clicking a tower selects it, clicking anywhere else or right clicking deselects.
+fn select_tower(
+    mouse: Res<Input<MouseButton>>,
+    cursor: Res<CursorPosition>,
+    over_ui: Res<CursorOverUi>,
+    towers: Query<(Entity, &Transform, &StructureRect), With<TowerType>>,
+    mut selected: ResMut<SelectedTower>,
+) {
+    if over_ui.0 {
+        return;
+    }
+    if mouse.just_pressed(MouseButton::Left) {
+        selected.0 = towers
+            .iter()
+            .find(|(_, transform, rect)| {
+                rect.to_hitbox()
+                    .with_translation(transform)
+                    .point_touches(&cursor.0)
+            })
+            .map(|(entity, _, _)| entity);
+    }
+    if mouse.just_pressed(MouseButton::Right) {
+        selected.0 = None;
+    }
+}
+
+/// What an upgrade changes on a tower
+type Upgradeable<'w, 's> = Query<
+    'w,
+    's,
+    (
+        &'static TowerType,
+        &'static mut UpgradeLevel,
+        Option<&'static mut BulletGenerator>,
+        Option<&'static mut Beam>,
+        &'static mut Aim,
+    ),
+>;
+
+fn upgrade_towers(
+    mut events: EventReader<UpgradeTower>,
+    mut towers: Upgradeable,
+    mut gold: ResMut<Gold>,
+    handle: Res<TowerDefinitionsHandle>,
+    definitions: Res<Assets<TowerDefinitions>>,
+) {
+    let definitions = match definitions.get(&handle.0) {
+        Some(definitions) => definitions,
+        None => return,
+    };
+    for UpgradeTower(entity) in events.iter() {
+        if let Ok((tower_type, mut level, generator, beam, mut aim)) = towers.get_mut(*entity) {
+            let tier = definitions
+                .get(&tower_type.0)
+                .and_then(|tower| tower.upgrades.get(level.0));
+            if let Some(tier) = tier {
+                if gold.buy(tier.cost) {
+                    if let Some(mut generator) = generator {
+                        apply_upgrade(tier, &mut generator, &mut aim);
+                    }
+                    if let Some(mut beam) = beam {
+                        apply_beam_upgrade(tier, &mut beam, &mut aim);
+                    }
+                    level.0 += 1;
+                }
+            }
+        }
+    }
+}
+
+fn sell_towers(
+    mut commands: Commands,
+    mut events: EventReader<SellTower>,
+    towers: Query<(&TowerType, &UpgradeLevel)>,
+    mut gold: ResMut<Gold>,
+    mut selected: ResMut<SelectedTower>,
+    handle: Res<TowerDefinitionsHandle>,
+    definitions: Res<Assets<TowerDefinitions>>,
+) {
+    let definitions = match definitions.get(&handle.0) {
+        Some(definitions) => definitions,
+        None => return,
+    };
+    for SellTower(entity) in events.iter() {
+        if let Ok((tower_type, level)) = towers.get(*entity) {
+            gold.0 += definitions.refund(&tower_type.0, level.0);
+            commands.entity(*entity).despawn_recursive();
+            if selected.0 == Some(*entity) {
+                selected.0 = None;
+            }
+        }
+    }
+}
+
+fn set_targeting(mut events: EventReader<SetTargeting>, mut towers: Query<&mut Targeting>) {
+    for SetTargeting(entity, mode) in events.iter() {
+        if let Ok(mut targeting) = towers.get_mut(*entity) {
+            *targeting = *mode;
+        }
+    }
+}
+
+pub fn apply_upgrade(tier: &UpgradeTier, generator: &mut BulletGenerator, aim: &mut Aim) {
+    let cooldown = (generator.cooldown.duration().as_secs_f32() + tier.cooldown).max(MIN_COOLDOWN);
+    generator.cooldown.set_duration(std::time::Duration::from_secs_f32(cooldown));
+    generator.bullet_damage += tier.damage;
+    generator.bullet_hits = (generator.bullet_hits as i32 + tier.hits).max(1) as u32;
+    generator.bullet_velocity += tier.velocity;
+    aim.radius += tier.range;
+}
+
+pub fn apply_beam_upgrade(tier: &UpgradeTier, beam: &mut Beam, aim: &mut Aim) {
+    beam.dps += tier.damage;
+    beam.chain = (beam.chain as i32 + tier.hits).max(0) as usize;
+    aim.radius += tier.range;
+}
+
+/// Nothing is selected at the start of a game, and requests left over from the last one are dropped.
+fn reset_selection(
+    mut selected: ResMut<SelectedTower>,
+    mut upgrades: ResMut<Events<UpgradeTower>>,
+    mut sells: ResMut<Events<SellTower>>,
+    mut targeting: ResMut<Events<SetTargeting>>,
+) {
+    *selected = SelectedTower::default();
+    upgrades.clear();
+    sells.clear();
+    targeting.clear();
+}
+
+fn spawn_range_indicator(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands
+        .spawn_bundle(SpriteBundle {
+            sprite: Sprite {
+                color: Color::rgba(1.0, 1.0, 1.0, 0.15),
+                ..Default::default()
+            },
+            texture: asset_server.load("circle.png"),
+            visibility: Visibility { is_visible: false },
+            ..Default::default()
+        })
+        .insert(RangeIndicator)
+        .insert(DespawnOnExit(AppState::Playing));
+}
+
+fn update_range_indicator(
+    selected: Res<SelectedTower>,
+    towers: Query<(&Transform, &Aim), Without<RangeIndicator>>,
+    mut indicator: Query<(&mut Transform, &mut Sprite, &mut Visibility), With<RangeIndicator>>,
+) {
+    for (mut transform, mut sprite, mut visibility) in indicator.iter_mut() {
+        match selected.0.and_then(|entity| towers.get(entity).ok()) {
+            Some((tower, aim)) => {
+                transform.translation = tower.translation.truncate().extend(2.0);
+                sprite.custom_size = Some(Vec2::splat(aim.radius * 2.0));
+                visibility.is_visible = true;
+            }
+            None => visibility.is_visible = false,
+        }
+    }
+}
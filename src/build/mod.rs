@@ -0,0 +1,428 @@
+use bevy::{
+    ecs::system::{EntityCommands, SystemParam},
+    prelude::*,
+};
+use thiserror::Error;
+
+use crate::{
+    actions::{PlayerAction, PlayerActions},
+    beam::Beam,
+    components::{
+        Aim, BulletGenerator, CursorOverUi, CursorPosition, DamageDealt, Gold, StructureRect,
+        Targeting,
+    },
+    map::{MapBounds, NoBuildZones},
+    pathfinding::NavPaths,
+    rectangle::Hitbox,
+    state::{AppState, DespawnOnExit},
+};
+
+use self::{
+    manage::{
+        apply_beam_upgrade, apply_upgrade, ManagePlugin, SelectedTower, TowerType, UpgradeLevel,
+    },
+    towerdata::{
+        BeamDefinition, BulletDefinition, LoadedTowers, TowerDefinition, TowerDefinitions,
+        TowerDefinitionsHandle, TowerDefinitionsLoader,
+    },
+};
+
+pub mod manage;
+pub mod towerdata;
+
+pub struct BuildPlugin;
+
+impl Plugin for BuildPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugin(ManagePlugin)
+            .add_asset::<TowerDefinitions>()
+            .init_asset_loader::<TowerDefinitionsLoader>()
+            .add_startup_system(load_tower_definitions)
+            .add_system_set(SystemSet::on_enter(AppState::Playing).with_system(spawn_indicator))
+            .add_system_set(
+                SystemSet::on_update(AppState::Playing)
+                    .with_system(refresh_indicator_tower)
+                    .with_system(indicator_overlap)
+                    .with_system(indicator_follow_mouse)
+                    .with_system(indicator_build.label(BuildSystem::Build))
+                    .with_system(indicator_resize)
+                    .with_system(indicator_recolour)
+                    .with_system(change_tower),
+            );
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, SystemLabel)]
+pub enum BuildSystem {
+    /// Placing new towers
+    Build,
+}
+
+/// Hotkeys for the towers in definition order
+const TOWER_KEYS: [KeyCode; 9] = [
+    KeyCode::V,
+    KeyCode::B,
+    KeyCode::C,
+    KeyCode::X,
+    KeyCode::Z,
+    KeyCode::F,
+    KeyCode::R,
+    KeyCode::Q,
+    KeyCode::E,
+];
+
+fn load_tower_definitions(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(TowerDefinitionsHandle(
+        asset_server.load("data/default.towers.ron"),
+    ));
+}
+
+fn spawn_indicator(mut commands: Commands) {
+    commands
+        .spawn()
+        .insert(BuildIndicator::default())
+        .insert(DespawnOnExit(AppState::Playing))
+        .insert_bundle(SpriteBundle {
+            sprite: Sprite {
+                color: Color::rgba(0.0, 0.5, 0.0, 0.5),
+                custom_size: Some(Vec2::splat(32.0)),
+                ..Default::default()
+            },
+            ..Default::default()
+        });
+}
+
+#[derive(Clone, Component, Default)]
+/// Marker for entity where building occurs
+pub struct BuildIndicator {
+    overlapping: bool,
+    out_of_bounds: bool,
+    //active: bool,
+    pub tower: Option<TowerDefinition>,
+}
+impl BuildIndicator {
+    pub fn can_build(&self) -> bool {
+        !self.overlapping && !self.out_of_bounds && self.tower.is_some()
+    }
+    fn extents(&self) -> Vec2 {
+        self.tower
+            .as_ref()
+            .map_or(Vec2::splat(32.0), |tower| tower.footprint())
+    }
+}
+
+/// Picks the first tower once definitions load, and keeps the held tower up to date when they change.
+/// Warns about towers past the end of `TOWER_KEYS`, which have no hotkey.
+fn refresh_indicator_tower(
+    mut events: EventReader<AssetEvent<TowerDefinitions>>,
+    definitions: Res<Assets<TowerDefinitions>>,
+    mut indicator: Query<&mut BuildIndicator>,
+) {
+    for event in events.iter() {
+        let handle = match event {
+            AssetEvent::Created { handle } | AssetEvent::Modified { handle } => handle,
+            AssetEvent::Removed { .. } => continue,
+        };
+        if let Some(definitions) = definitions.get(handle) {
+            if definitions.towers.len() > TOWER_KEYS.len() {
+                warn!(
+                    "Only the first {} towers get hotkeys, {} can only be picked from the build buttons",
+                    TOWER_KEYS.len(),
+                    definitions.towers.len() - TOWER_KEYS.len()
+                );
+            }
+            for mut indicator in indicator.iter_mut() {
+                let tower = match &indicator.tower {
+                    Some(tower) => definitions.get(&tower.id),
+                    None => definitions.towers.first(),
+                };
+                indicator.tower = tower.cloned();
+            }
+        }
+    }
+}
+
+fn indicator_follow_mouse(
+    mouse: Res<CursorPosition>,
+    bounds: Res<MapBounds>,
+    mut indicator: Query<(&mut Transform, &mut BuildIndicator)>,
+) {
+    for (mut transform, mut indicator) in indicator.iter_mut() {
+        transform.translation = bounds.clamp(mouse.0).extend(3.0);
+        indicator.out_of_bounds = !bounds.contains(mouse.0);
+    }
+}
+
+/// What's in the way of building, checked both under the indicator and when a tower is placed
+#[derive(SystemParam)]
+pub struct BuildSite<'w, 's> {
+    structures: Query<'w, 's, (&'static Transform, &'static StructureRect), Without<BuildIndicator>>,
+    paths: Res<'w, NavPaths>,
+    no_build: Res<'w, NoBuildZones>,
+    bounds: Res<'w, MapBounds>,
+}
+impl<'w, 's> BuildSite<'w, 's> {
+    /// Whether a tower with `footprint` at `position` would touch another tower, a path or a
+    /// no-build zone.
+    pub fn is_blocked(&self, position: Vec2, footprint: Vec2) -> bool {
+        let rect = Hitbox::with_extents(footprint).with_offset(position);
+        let on_structure = self.structures.iter().any(|(structure_transform, structure)| {
+            structure
+                .to_hitbox()
+                .with_translation(structure_transform)
+                .touches(&rect)
+        });
+        let on_path = self
+            .paths
+            .iter()
+            .flat_map(|path| path.iter())
+            .any(|x| x.distance_squared(position) < (footprint.x / 2.0 + 20.0).powi(2));
+        on_structure || on_path || self.no_build.touches(&rect)
+    }
+    pub fn in_bounds(&self, position: Vec2) -> bool {
+        self.bounds.contains(position)
+    }
+}
+
+fn indicator_overlap(
+    mut indicator: Query<(&Transform, &mut BuildIndicator)>,
+    site: BuildSite,
+    gold: Res<Gold>,
+) {
+    for (indicator_transform, mut indicator) in indicator.iter_mut() {
+        let blocked = site.is_blocked(indicator_transform.translation.truncate(), indicator.extents());
+        let affordable = indicator
+            .tower
+            .as_ref()
+            .is_some_and(|tower| tower.cost <= gold.0);
+        indicator.overlapping = blocked || !affordable;
+    }
+}
+
+/// Asks for the held tower to be placed under the indicator. It's paid for and built on the next tick.
+fn indicator_build(
+    indicator: Query<(&Transform, &BuildIndicator)>,
+    mut actions: ResMut<PlayerActions>,
+    input: Res<Input<KeyCode>>,
+    mouse: Res<Input<MouseButton>>,
+    over_ui: Res<CursorOverUi>,
+    selected: Res<SelectedTower>,
+) {
+    let clicked = mouse.just_pressed(MouseButton::Left) && !over_ui.0;
+    for (transform, indicator) in indicator.iter() {
+        if let Some(tower) = &indicator.tower {
+            // Clicking away from a selected tower only deselects it
+            if (input.just_pressed(KeyCode::T) || (clicked && selected.0.is_none()))
+                && indicator.can_build()
+            {
+                actions.push(PlayerAction::PlaceTower {
+                    tower: tower.id.clone(),
+                    position: transform.translation.truncate(),
+                });
+            }
+        }
+    }
+}
+
+fn indicator_resize(mut indicator: Query<(&BuildIndicator, &mut Sprite)>) {
+    for (indicator, mut sprite) in indicator.iter_mut() {
+        sprite.custom_size = Some(indicator.extents());
+    }
+}
+
+fn indicator_recolour(mut indicator: Query<(&BuildIndicator, &mut Sprite)>) {
+    for (indicator, mut sprite) in indicator.iter_mut() {
+        if indicator.can_build() {
+            sprite.color = Color::rgba(0.0, 0.5, 0.0, 0.5);
+        } else {
+            sprite.color = Color::rgba(0.5, 0.0, 0.0, 0.5);
+        }
+    }
+}
+
+fn change_tower(
+    mut indicator: Query<&mut BuildIndicator>,
+    input: Res<Input<KeyCode>>,
+    handle: Res<TowerDefinitionsHandle>,
+    definitions: Res<Assets<TowerDefinitions>>,
+) {
+    let definitions = match definitions.get(&handle.0) {
+        Some(definitions) => definitions,
+        None => return,
+    };
+    for mut indicator in indicator.iter_mut() {
+        for (key, tower) in TOWER_KEYS.iter().zip(definitions.towers.iter()) {
+            if input.just_pressed(*key) {
+                indicator.tower = Some(tower.clone());
+            }
+        }
+    }
+}
+
+/// Everything a tower is spawned with but its attack, see `spawn`.
+#[derive(Clone)]
+pub struct TowerBundle {
+    sprite_bundle: SpriteBundle,
+    aim: Aim,
+    targeting: Targeting,
+    structure_rect: StructureRect,
+    gold: Gold,
+    tower_type: TowerType,
+    upgrade_level: UpgradeLevel,
+    damage_dealt: DamageDealt,
+}
+impl TowerBundle {
+    pub fn spawn<'w, 's, 'a>(self, commands: &'a mut Commands<'w, 's>) -> EntityCommands<'w, 's, 'a> {
+        let mut tower = commands.spawn_bundle(self.sprite_bundle);
+        tower.insert_bundle((
+            self.aim,
+            self.targeting,
+            self.structure_rect,
+            self.gold,
+            self.tower_type,
+            self.upgrade_level,
+            self.damage_dealt,
+        ));
+        tower
+    }
+    pub fn from_definition(definition: &TowerDefinition) -> Self {
+        Self {
+            sprite_bundle: SpriteBundle {
+                sprite: Sprite {
+                    color: definition.color,
+                    custom_size: Some(definition.size),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            aim: Aim::new(definition.range).with_lead(definition.lead),
+            targeting: definition.targeting,
+            structure_rect: StructureRect::from_vec2(definition.footprint()),
+            gold: Gold(definition.cost),
+            tower_type: TowerType(definition.id.clone()),
+            upgrade_level: UpgradeLevel(0),
+            damage_dealt: DamageDealt::default(),
+        }
+    }
+}
+
+pub fn bullet_generator(bullet: &BulletDefinition, asset_server: &AssetServer) -> BulletGenerator {
+    BulletGenerator {
+        cooldown: Timer::from_seconds(bullet.cooldown, true),
+        bullet_velocity: bullet.velocity,
+        bullet_lifespan: bullet.lifespan,
+        bullet_damage: bullet.damage,
+        bullet_damage_type: bullet.damage_type,
+        bullet_hits: bullet.hits,
+        bullet_extents: bullet.extents,
+        bullet_texture: match &bullet.texture {
+            Some(path) => asset_server.load(path.as_str()),
+            None => BulletGenerator::default().bullet_texture,
+        },
+        bullet_homing: bullet.homing,
+        bullet_explosion: bullet.explosion,
+        bullet_effects: bullet.effects.clone(),
+        ..Default::default()
+    }
+}
+
+pub fn beam(beam: &BeamDefinition) -> Beam {
+    Beam::new(beam.dps, beam.damage_type)
+        .with_chain(beam.chain, beam.chain_range)
+        .with_effects(beam.effects.clone())
+        .with_looks(beam.color, beam.width)
+}
+
+/// Places towers the player has bought
+#[derive(SystemParam)]
+pub struct TowerBuilder<'w, 's> {
+    commands: Commands<'w, 's>,
+    towers: LoadedTowers<'w, 's>,
+    site: BuildSite<'w, 's>,
+    asset_server: Res<'w, AssetServer>,
+    /// Footprints of towers placed this tick, which the site can't see until the commands run
+    #[system_param(ignore)]
+    placed: Vec<Hitbox>,
+}
+impl<'w, 's> TowerBuilder<'w, 's> {
+    /// Buys tower `id` and places it at `position`, if there's enough gold and room. The
+    /// indicator checks this too, but the map can change between a click and the tick it's
+    /// carried out on, and several clicks can land on the same tick.
+    pub fn place(&mut self, id: &str, position: Vec2, gold: &mut Gold) -> Result<(), PlaceError> {
+        let definition = self
+            .towers
+            .get()
+            .and_then(|towers| towers.get(id))
+            .ok_or_else(|| PlaceError::UnknownTower(id.to_string()))?;
+        let footprint = definition.footprint();
+        let rect = Hitbox::with_extents(footprint).with_offset(position);
+        if !self.site.in_bounds(position) {
+            return Err(PlaceError::OutOfBounds);
+        }
+        if self.site.is_blocked(position, footprint) || self.placed.iter().any(|placed| placed.touches(&rect)) {
+            return Err(PlaceError::Blocked);
+        }
+        if !gold.buy(definition.cost) {
+            return Err(PlaceError::NotEnoughGold);
+        }
+        spawn_tower(&mut self.commands, definition, position.extend(1.0), &self.asset_server);
+        self.placed.push(rect);
+        Ok(())
+    }
+}
+
+/// Why `TowerBuilder::place` didn't place a tower
+#[derive(Debug, Error)]
+pub enum PlaceError {
+    #[error("unknown tower \"{0}\"")]
+    UnknownTower(String),
+    #[error("outside the map")]
+    OutOfBounds,
+    #[error("in the way of another tower, a path or a no-build zone")]
+    Blocked,
+    #[error("not enough gold")]
+    NotEnoughGold,
+}
+
+/// Spawns a tower with whichever attack its definition has.
+pub fn spawn_tower(
+    commands: &mut Commands,
+    definition: &TowerDefinition,
+    translation: Vec3,
+    asset_server: &AssetServer,
+) -> Entity {
+    spawn_upgraded_tower(commands, definition, translation, 0, asset_server)
+}
+
+/// Spawns a tower with its first `level` upgrade tiers already bought, as when loading a save.
+pub fn spawn_upgraded_tower(
+    commands: &mut Commands,
+    definition: &TowerDefinition,
+    translation: Vec3,
+    level: usize,
+    asset_server: &AssetServer,
+) -> Entity {
+    let mut bundle = TowerBundle::from_definition(definition);
+    let mut generator = definition.bullet.as_ref().map(|bullet| bullet_generator(bullet, asset_server));
+    let mut beam = definition.beam.as_ref().map(beam);
+    for tier in definition.upgrades.iter().take(level) {
+        if let Some(generator) = generator.as_mut() {
+            apply_upgrade(tier, generator, &mut bundle.aim);
+        }
+        if let Some(beam) = beam.as_mut() {
+            apply_beam_upgrade(tier, beam, &mut bundle.aim);
+        }
+        bundle.upgrade_level.0 += 1;
+    }
+    let mut tower = bundle.spawn(commands);
+    tower
+        .insert(Transform::from_translation(translation))
+        .insert(DespawnOnExit(AppState::Playing));
+    if let Some(generator) = generator {
+        tower.insert(generator);
+    }
+    if let Some(beam) = beam {
+        tower.insert(beam);
+    }
+    tower.id()
+}
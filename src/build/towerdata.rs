@@ -0,0 +1,201 @@
+use std::marker::PhantomData;
+
+use bevy::{
+    asset::{AssetLoader, BoxedFuture, LoadContext, LoadedAsset},
+    ecs::system::SystemParam,
+    prelude::*,
+    reflect::TypeUuid,
+};
+use serde::Deserialize;
+
+use crate::{
+    components::{DamageType, Targeting},
+    effects::StatusEffect,
+};
+
+/// Every tower the player can build, loaded from a `.towers.ron` file.
+/// The order of `towers` is the order of the build buttons and hotkeys.
+/// Start the file with `#![enable(implicit_some)]` to leave `Some(..)` off optional fields.
+#[derive(Debug, Clone, Deserialize, TypeUuid)]
+#[uuid = "be16ea68-5584-47d3-97b8-eb0012c8a5e7"]
+pub struct TowerDefinitions {
+    pub towers: Vec<TowerDefinition>,
+    /// Fraction of the gold invested in a tower given back when it's sold.
+    #[serde(default = "TowerDefinitions::default_sell_fraction")]
+    pub sell_fraction: f32,
+}
+impl TowerDefinitions {
+    pub fn get(&self, id: &str) -> Option<&TowerDefinition> {
+        self.towers.iter().find(|tower| tower.id == id)
+    }
+    /// Gold paid back for selling a tower of type `id` with `level` upgrades bought.
+    pub fn refund(&self, id: &str, level: usize) -> u32 {
+        self.get(id)
+            .map_or(0, |tower| (tower.invested(level) as f32 * self.sell_fraction) as u32)
+    }
+    fn default_sell_fraction() -> f32 {
+        0.75
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct TowerDefinition {
+    /// Unique key used to refer to this tower from other data.
+    pub id: String,
+    /// Name displayed in the UI.
+    pub name: String,
+    pub cost: u32,
+    pub color: Color,
+    pub size: Vec2,
+    /// Area blocked from building, defaults to the sprite size.
+    #[serde(default)]
+    pub footprint: Option<Vec2>,
+    pub range: f32,
+    /// Targeting mode the tower starts with.
+    #[serde(default)]
+    pub targeting: Targeting,
+    /// Shoot ahead of moving targets so slow bullets still hit.
+    #[serde(default)]
+    pub lead: bool,
+    /// How the tower attacks, every tower needs exactly one of these.
+    #[serde(default)]
+    pub bullet: Option<BulletDefinition>,
+    #[serde(default)]
+    pub beam: Option<BeamDefinition>,
+    /// Tiers bought in order after the tower is placed.
+    #[serde(default)]
+    pub upgrades: Vec<UpgradeTier>,
+}
+impl TowerDefinition {
+    pub fn footprint(&self) -> Vec2 {
+        self.footprint.unwrap_or(self.size)
+    }
+    pub fn label(&self) -> String {
+        format!("{} (${})", self.name, self.cost)
+    }
+    /// Total gold spent on this tower after buying `level` upgrades.
+    pub fn invested(&self, level: usize) -> u32 {
+        self.cost + self.upgrades.iter().take(level).map(|tier| tier.cost).sum::<u32>()
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct BulletDefinition {
+    pub cooldown: f32,
+    /// Units per second.
+    pub velocity: f32,
+    pub lifespan: f32,
+    pub damage: f32,
+    #[serde(default)]
+    pub damage_type: DamageType,
+    pub hits: u32,
+    #[serde(default = "BulletDefinition::default_extents")]
+    pub extents: Vec2,
+    /// Path of the bullet image relative to the assets folder.
+    #[serde(default)]
+    pub texture: Option<String>,
+    /// Turn rate in radians per second for bullets that chase their target.
+    #[serde(default)]
+    pub homing: Option<f32>,
+    /// Blast radius for bullets that explode on contact or when their lifespan ends,
+    /// hurting everything nearby instead of just what they hit.
+    #[serde(default)]
+    pub explosion: Option<f32>,
+    /// Applied to every unit the bullet damages.
+    #[serde(default)]
+    pub effects: Vec<StatusEffect>,
+}
+impl BulletDefinition {
+    fn default_extents() -> Vec2 {
+        Vec2::splat(8.0)
+    }
+}
+
+/// A continuous attack, see `Beam`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct BeamDefinition {
+    /// Damage per second to every unit in the beam.
+    pub dps: f32,
+    #[serde(default)]
+    pub damage_type: DamageType,
+    /// Extra units the beam jumps to after its target.
+    #[serde(default)]
+    pub chain: usize,
+    #[serde(default = "BeamDefinition::default_chain_range")]
+    pub chain_range: f32,
+    #[serde(default)]
+    pub effects: Vec<StatusEffect>,
+    #[serde(default = "BeamDefinition::default_color")]
+    pub color: Color,
+    #[serde(default = "BeamDefinition::default_width")]
+    pub width: f32,
+}
+impl BeamDefinition {
+    fn default_chain_range() -> f32 {
+        150.0
+    }
+    fn default_color() -> Color {
+        Color::rgb(1.0, 0.2, 0.2)
+    }
+    fn default_width() -> f32 {
+        6.0
+    }
+}
+
+/// Changes applied to a placed tower when an upgrade is bought.
+/// Every stat is added to the tower's current value. Beams add `damage` to their
+/// damage per second and `hits` to their chain.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct UpgradeTier {
+    pub cost: u32,
+    /// Seconds between shots, negative to shoot faster.
+    pub cooldown: f32,
+    pub damage: f32,
+    pub hits: i32,
+    pub velocity: f32,
+    pub range: f32,
+}
+
+#[derive(Default)]
+pub struct TowerDefinitionsLoader;
+
+impl AssetLoader for TowerDefinitionsLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), anyhow::Error>> {
+        Box::pin(async move {
+            let towers = ron::de::from_bytes::<TowerDefinitions>(bytes)?;
+            for tower in towers.towers.iter() {
+                if tower.bullet.is_some() == tower.beam.is_some() {
+                    anyhow::bail!("tower \"{}\" needs either a bullet or a beam", tower.id);
+                }
+            }
+            load_context.set_default_asset(LoadedAsset::new(towers));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["towers.ron"]
+    }
+}
+
+/// Handle to the tower definitions used by the current game
+pub struct TowerDefinitionsHandle(pub Handle<TowerDefinitions>);
+
+/// The tower definitions used by the current game, once they've loaded
+#[derive(SystemParam)]
+pub struct LoadedTowers<'w, 's> {
+    handle: Res<'w, TowerDefinitionsHandle>,
+    definitions: Res<'w, Assets<TowerDefinitions>>,
+    #[system_param(ignore)]
+    marker: PhantomData<&'s ()>,
+}
+impl<'w, 's> LoadedTowers<'w, 's> {
+    pub fn get(&self) -> Option<&TowerDefinitions> {
+        self.definitions.get(&self.handle.0)
+    }
+}
@@ -1,29 +1,48 @@
 use std::time::Duration;
 
-use bevy::prelude::*;
+use bevy::{app::Events, prelude::*};
 
-use crate::rectangle::Hitbox;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    effects::{Inflicts, StatusEffect, StatusEffects},
+    pathfinding::{NavPaths, PathFollow},
+    rectangle::Hitbox,
+    simulation::{SimulationAppExt, SimulationClock, SimulationSystem},
+    spatial::SpatialGrid,
+    stages::{CurrentStage, StageUnit},
+    state::{AppState, DespawnOnExit},
+};
 
 pub struct ComponentsPlugin;
 
 impl Plugin for ComponentsPlugin {
     fn build(&self, app: &mut App) {
         app.init_resource::<CursorPosition>()
+            .init_resource::<CursorOverUi>()
+            .init_resource::<StartingResources>()
             .init_resource::<Gold>()
-            .insert_resource(Lives(100))
-            .add_system(apply_velocity)
-            .add_system(bullet_generator)
-            .add_system(aim_bullet_generators)
+            .init_resource::<Lives>()
+            .add_event::<ExplosionEvent>()
+            .init_resource::<SpatialGrid>()
+            .add_system_set(SystemSet::on_enter(AppState::Playing).with_system(reset_resources))
+            .add_simulation_system(SimulationSystem::Steer, steer_homing)
+            .add_simulation_system(SimulationSystem::Move, apply_velocity)
+            .add_simulation_system(SimulationSystem::Index, index_damage_absorbers)
+            .add_simulation_system(SimulationSystem::Aim, aim_bullet_generators)
+            .add_simulation_system(SimulationSystem::Shoot, bullet_generator)
+            .add_simulation_system(SimulationSystem::Collide, absorb_bullets)
+            .add_simulation_system(SimulationSystem::Explode, explode)
+            .add_simulation_system(SimulationSystem::Expire, update_lifespan)
+            .add_simulation_system(SimulationSystem::Cleanup, despawn_dead)
             .add_system(update_cursor_position)
-            .add_system(update_lifespan)
-            .add_system(despawn_dead)
-            .add_system(absorb_bullets)
+            .add_system(fade_explosions)
             .add_system(rotate_bullets);
     }
 }
 
 #[derive(Copy, Clone, Debug, Component, Reflect)]
-/// Regular old velocity that obeys Newton's first law.
+/// Regular old velocity that obeys Newton's first law, in units per second.
 pub struct Velocity {
     pub velocity: Vec3,
 }
@@ -38,9 +57,9 @@ impl Velocity {
         Self { velocity }
     }
 }
-fn apply_velocity(mut query: Query<(&mut Transform, &Velocity)>) {
+fn apply_velocity(mut query: Query<(&mut Transform, &Velocity)>, clock: Res<SimulationClock>) {
     for (mut transform, velocity) in query.iter_mut() {
-        transform.translation += velocity.velocity;
+        transform.translation += velocity.velocity * clock.delta_seconds();
     }
 }
 
@@ -52,9 +71,18 @@ pub struct BulletGenerator {
     pub bullet_velocity: f32,
     pub bullet_lifespan: f32,
     pub bullet_damage: f32,
+    pub bullet_damage_type: DamageType,
     pub bullet_hits: u32,
     pub bullet_extents: Vec2,
     pub bullet_texture: Handle<Image>,
+    /// Turn rate of the bullets in radians per second, if they home in on their target.
+    pub bullet_homing: Option<f32>,
+    /// Blast radius of the bullets, if they explode
+    pub bullet_explosion: Option<f32>,
+    /// Status effects the bullets apply on hit
+    pub bullet_effects: Vec<StatusEffect>,
+    /// Unit currently being aimed at
+    pub target: Option<Entity>,
 }
 
 impl Default for BulletGenerator {
@@ -63,27 +91,32 @@ impl Default for BulletGenerator {
             aim: Vec2::new(1.0, 0.0),
             cooldown: Timer::from_seconds(1.0, true),
             shooting: true,
-            bullet_velocity: 1.0,
+            bullet_velocity: 60.0,
             bullet_lifespan: 5.0,
             bullet_damage: 1.0,
+            bullet_damage_type: DamageType::default(),
             bullet_hits: 1,
             bullet_extents: Vec2::splat(8.0),
             bullet_texture: bevy::render::texture::DEFAULT_IMAGE_HANDLE.typed(),
+            bullet_homing: None,
+            bullet_explosion: None,
+            bullet_effects: Vec::new(),
+            target: None,
         }
     }
 }
 
 fn bullet_generator(
     mut commands: Commands,
-    mut generators: Query<(&mut BulletGenerator, &Transform)>,
-    time: Res<Time>,
+    mut generators: Query<(Entity, &mut BulletGenerator, &Transform)>,
+    clock: Res<SimulationClock>,
 ) {
-    for (mut generator, transform) in generators.iter_mut() {
-        generator.cooldown.tick(time.delta());
+    for (tower, mut generator, transform) in generators.iter_mut() {
+        generator.cooldown.tick(clock.delta());
         if generator.cooldown.finished() && generator.shooting {
             generator.cooldown.reset();
-            commands
-                .spawn()
+            let mut bullet = commands.spawn();
+            bullet
                 .insert_bundle(SpriteBundle {
                     sprite: Sprite {
                         color: Color::rgb(0.0, 0.0, 0.0),
@@ -100,11 +133,25 @@ fn bullet_generator(
                     (generator.aim * generator.bullet_velocity).extend(0.0),
                 ))
                 .insert(Lifespan::new(generator.bullet_lifespan))
-                .insert(Bullet::new(
-                    generator.bullet_extents,
-                    generator.bullet_damage,
-                    generator.bullet_hits,
-                ));
+                .insert(DespawnOnExit(AppState::Playing))
+                .insert(
+                    Bullet::new(
+                        generator.bullet_extents,
+                        generator.bullet_damage,
+                        generator.bullet_hits,
+                    )
+                    .with_damage_type(generator.bullet_damage_type)
+                    .with_source(tower),
+                );
+            if let Some(turn_rate) = generator.bullet_homing {
+                bullet.insert(Homing::new(generator.target, turn_rate));
+            }
+            if let Some(radius) = generator.bullet_explosion {
+                bullet.insert(Explosive::new(radius));
+            }
+            if !generator.bullet_effects.is_empty() {
+                bullet.insert(Inflicts(generator.bullet_effects.clone()));
+            }
         }
     }
 }
@@ -112,40 +159,173 @@ fn bullet_generator(
 #[derive(Clone, Debug, Component, Reflect)]
 pub struct Aim {
     pub radius: f32,
+    /// Aim where the target will be when the bullet gets there, instead of where it is now.
+    pub lead: bool,
 }
 impl Aim {
     pub fn new(radius: f32) -> Self {
-        Self { radius }
+        Self {
+            radius,
+            lead: false,
+        }
+    }
+    pub fn with_lead(mut self, lead: bool) -> Self {
+        self.lead = lead;
+        self
+    }
+}
+
+/// Point where a bullet fired from `source` at `speed` meets a target at `target` moving
+/// with `velocity`, or `None` if the bullet can't catch it.
+fn intercept(source: Vec2, target: Vec2, velocity: Vec2, speed: f32) -> Option<Vec2> {
+    let offset = target - source;
+    // Solve |offset + velocity * t| = speed * t for the earliest positive t
+    let a = velocity.length_squared() - speed * speed;
+    let b = 2.0 * offset.dot(velocity);
+    let c = offset.length_squared();
+    let time = if a.abs() < f32::EPSILON {
+        if b.abs() < f32::EPSILON {
+            return None;
+        }
+        -c / b
+    } else {
+        let discriminant = b * b - 4.0 * a * c;
+        if discriminant < 0.0 {
+            return None;
+        }
+        let root = discriminant.sqrt();
+        let (t1, t2) = ((-b - root) / (2.0 * a), (-b + root) / (2.0 * a));
+        match (t1 > 0.0, t2 > 0.0) {
+            (true, true) => t1.min(t2),
+            (true, false) => t1,
+            (false, true) => t2,
+            (false, false) => return None,
+        }
+    };
+    (time > 0.0).then(|| target + velocity * time)
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Component, Reflect, Deserialize, Serialize)]
+/// Which unit in range a tower shoots at
+pub enum Targeting {
+    /// Furthest along its path
+    First,
+    /// Least far along its path
+    Last,
+    /// Most health
+    Strongest,
+    /// Least health
+    Weakest,
+    #[default]
+    Closest,
+}
+impl Targeting {
+    pub const ALL: [Targeting; 5] = [
+        Targeting::First,
+        Targeting::Last,
+        Targeting::Strongest,
+        Targeting::Weakest,
+        Targeting::Closest,
+    ];
+    /// The mode after this one, for cycling through them.
+    pub fn next(self) -> Self {
+        let index = Self::ALL.iter().position(|mode| *mode == self).unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+    pub fn name(self) -> &'static str {
+        match self {
+            Targeting::First => "First",
+            Targeting::Last => "Last",
+            Targeting::Strongest => "Strongest",
+            Targeting::Weakest => "Weakest",
+            Targeting::Closest => "Closest",
+        }
+    }
+    /// Score of a candidate target, the lowest score gets shot.
+    fn score(self, distance_squared: f32, health: f32, distance_left: f32) -> f32 {
+        match self {
+            Targeting::First => distance_left,
+            Targeting::Last => -distance_left,
+            Targeting::Strongest => -health,
+            Targeting::Weakest => health,
+            Targeting::Closest => distance_squared,
+        }
     }
 }
+/// Units a tower could shoot at
+pub type Targets<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Entity,
+        &'static Transform,
+        &'static Health,
+        Option<&'static PathFollow>,
+        Option<&'static Velocity>,
+    ),
+    With<AiUnit>,
+>;
+
+/// The unit within `aim` of `source` that `targeting` likes best.
+pub fn choose_target<'a>(
+    targets: &'a Targets,
+    grid: &SpatialGrid,
+    paths: &NavPaths,
+    source: Vec3,
+    aim: &Aim,
+    targeting: Targeting,
+) -> Option<(Entity, &'a Transform, Option<&'a Velocity>)> {
+    let mut nearby = Vec::new();
+    grid.query_radius(source.truncate(), aim.radius, &mut nearby);
+    nearby
+        .into_iter()
+        .filter_map(|entity| targets.get(entity).ok())
+        .filter_map(|(entity, target, health, path_follow, velocity)| {
+            let distance_squared = target.translation.distance_squared(source);
+            if distance_squared > aim.radius.powi(2) {
+                return None;
+            }
+            let distance_left = path_follow.map_or(0.0, |path_follow| {
+                path_follow.distance_left(target.translation.truncate(), paths)
+            });
+            let score = targeting.score(distance_squared, health.health, distance_left);
+            Some((entity, target, velocity, score))
+        })
+        .reduce(|x, y| if x.3 > y.3 { y } else { x })
+        .map(|(entity, target, velocity, _)| (entity, target, velocity))
+}
 
 fn aim_bullet_generators(
-    mut generators: Query<(&mut BulletGenerator, &Transform, &Aim)>,
-    targets: Query<&Transform, With<AiUnit>>,
+    mut generators: Query<(&mut BulletGenerator, &Transform, &Aim, Option<&Targeting>)>,
+    targets: Targets,
+    grid: Res<SpatialGrid>,
+    paths: Res<NavPaths>,
 ) {
-    for (mut generator, transform, aim) in generators.iter_mut() {
+    for (mut generator, transform, aim, targeting) in generators.iter_mut() {
+        let targeting = targeting.copied().unwrap_or_default();
         let source = transform.translation;
-        let target = targets.iter().reduce(|x, y| {
-            if x.translation.distance_squared(source) > y.translation.distance_squared(source) {
-                y
-            } else {
-                x
-            }
-        });
-        if let Some(target) = target {
-            if aim.radius.powi(2) >= target.translation.distance_squared(source) {
-                generator.cooldown.set_repeating(true);
-                generator.shooting = true;
-                let target = target.translation.truncate();
-                let source = source.truncate();
-                generator.aim = (target - source).normalize();
-            } else {
-                generator.cooldown.set_repeating(false);
-                generator.shooting = false;
+        let target = choose_target(&targets, &grid, &paths, source, aim, targeting);
+        if let Some((entity, target, velocity)) = target {
+            generator.target = Some(entity);
+            generator.cooldown.set_repeating(true);
+            generator.shooting = true;
+            let source = source.truncate();
+            let mut target = target.translation.truncate();
+            if aim.lead {
+                if let Some(velocity) = velocity {
+                    let velocity = velocity.velocity.truncate();
+                    if let Some(lead) =
+                        intercept(source, target, velocity, generator.bullet_velocity)
+                    {
+                        target = lead;
+                    }
+                }
             }
+            generator.aim = (target - source).normalize();
         } else {
             generator.cooldown.set_repeating(false);
             generator.shooting = false;
+            generator.target = None;
         };
     }
 }
@@ -162,6 +342,10 @@ pub struct MainCamera;
 #[derive(Debug, Clone, Default)]
 pub struct CursorPosition(pub Vec2);
 
+/// Whether the mouse is over a UI panel, so clicks shouldn't reach the map
+#[derive(Debug, Clone, Default)]
+pub struct CursorOverUi(pub bool);
+
 /// "Get mouse window position" example from the unofficial bevy cheat sheet docs (https://bevy-cheatbook.github.io/cookbook/cursor2world.html)
 fn update_cursor_position(
     // need to get window dimensions
@@ -170,13 +354,16 @@ fn update_cursor_position(
     camera: Query<&Transform, With<MainCamera>>,
     mut cursor_position: ResMut<CursorPosition>,
 ) {
-    // get the primary window
-    let wnd = windows.get_primary().unwrap();
+    // get the primary window, there isn't one when running headless
+    let wnd = match windows.get_primary() {
+        Some(wnd) => wnd,
+        None => return,
+    };
 
     // check if the cursor is in the primary window
     if let Some(pos) = wnd.cursor_position() {
         // get the size of the window
-        let size = Vec2::new(wnd.width() as f32, wnd.height() as f32);
+        let size = Vec2::new(wnd.width(), wnd.height());
 
         // the default orthographic projection is in pixels from the center;
         // just undo the translation
@@ -208,16 +395,38 @@ impl Lifespan {
     pub fn finished(&self) -> bool {
         self.0.finished()
     }
+    /// How much of the lifespan has passed, from 0 to 1
+    pub fn percent(&self) -> f32 {
+        self.0.percent()
+    }
 }
 
+/// Anything with a lifespan, and what it needs to explode when it runs out
+type Expiring<'w, 's> = Query<
+    'w,
+    's,
+    (
+        &'static mut Lifespan,
+        Entity,
+        Option<&'static Bullet>,
+        Option<&'static Explosive>,
+        Option<&'static Transform>,
+        Option<&'static Inflicts>,
+    ),
+>;
+
 pub fn update_lifespan(
     mut commands: Commands,
-    mut query: Query<(&mut Lifespan, Entity)>,
-    time: Res<Time>,
+    mut query: Expiring,
+    mut explosions: EventWriter<ExplosionEvent>,
+    clock: Res<SimulationClock>,
 ) {
-    for (mut life, entity) in query.iter_mut() {
-        life.tick(time.delta());
+    for (mut life, entity, bullet, explosive, transform, inflicts) in query.iter_mut() {
+        life.tick(clock.delta());
         if life.finished() {
+            if let (Some(bullet), Some(explosive), Some(transform)) = (bullet, explosive, transform) {
+                explosions.send(explosive.explosion(transform, bullet, inflicts));
+            }
             commands.entity(entity).despawn_recursive();
         };
     }
@@ -238,25 +447,104 @@ impl Health {
             ignore_death: false,
         }
     }
-    pub fn damage(&mut self, amount: f32) {
-        if !self.ignore_damage {
-            self.health -= amount;
+    /// Takes `amount` off, returning how much health was actually lost.
+    pub fn damage(&mut self, amount: f32) -> f32 {
+        if self.ignore_damage {
+            return 0.0;
         }
+        let lost = amount.min(self.health.max(0.0));
+        self.health -= amount;
+        lost
     }
     pub fn dead(&self) -> bool {
         self.health <= 0.0 && !self.ignore_death
     }
 }
 
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Reflect, Deserialize)]
+pub enum DamageType {
+    #[default]
+    Physical,
+    Piercing,
+    Magic,
+    Explosive,
+}
+impl DamageType {
+    pub fn name(self) -> &'static str {
+        match self {
+            DamageType::Physical => "physical",
+            DamageType::Piercing => "piercing",
+            DamageType::Magic => "magic",
+            DamageType::Explosive => "explosive",
+        }
+    }
+}
+
+/// Fraction of each damage type an enemy ignores, from 0 to 1
+#[derive(Clone, Copy, Debug, Default, Reflect, Deserialize)]
+#[serde(default)]
+pub struct Resistances {
+    pub physical: f32,
+    pub piercing: f32,
+    pub magic: f32,
+    pub explosive: f32,
+}
+impl Resistances {
+    pub fn get(&self, damage_type: DamageType) -> f32 {
+        match damage_type {
+            DamageType::Physical => self.physical,
+            DamageType::Piercing => self.piercing,
+            DamageType::Magic => self.magic,
+            DamageType::Explosive => self.explosive,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, Component, Reflect)]
+/// Reduces incoming damage. Resistances scale every hit of their type, then flat armor
+/// is taken off physical hits. Something always gets through.
+pub struct Armor {
+    pub armor: f32,
+    pub resistances: Resistances,
+}
+impl Armor {
+    /// Smallest fraction of a hit that armor can't stop
+    const MIN_DAMAGE: f32 = 0.1;
+
+    pub fn new(armor: f32, resistances: Resistances) -> Self {
+        Self { armor, resistances }
+    }
+    pub fn reduce(&self, amount: f32, damage_type: DamageType) -> f32 {
+        let mut reduced = amount * (1.0 - self.resistances.get(damage_type).clamp(0.0, 1.0));
+        if damage_type == DamageType::Physical {
+            reduced -= self.armor;
+        }
+        reduced.max(amount * Self::MIN_DAMAGE)
+    }
+}
+
+/// Damage after the target's armor, if it has any
+pub fn mitigated(amount: f32, damage_type: DamageType, armor: Option<&Armor>) -> f32 {
+    armor.map_or(amount, |armor| armor.reduce(amount, damage_type))
+}
+
+#[derive(Debug, Clone, Default, Component, Reflect)]
+/// Running total of the damage a tower has done
+pub struct DamageDealt(pub f32);
+
 pub fn despawn_dead(
     mut commands: Commands,
-    mut query: Query<(&Health, Entity, Option<&Gold>)>,
-    mut gold_resource: ResMut<Gold>) {
-    for (health, entity, gold) in query.iter_mut() {
+    mut query: Query<(&Health, Entity, Option<&Gold>, Option<&StageUnit>)>,
+    mut gold_resource: ResMut<Gold>,
+    mut current_stage: ResMut<CurrentStage>) {
+    for (health, entity, gold, stage) in query.iter_mut() {
         if health.dead() {
             if let Some(gold) = gold {
                 gold_resource.0 += gold.0;
             }
+            if let Some(stage) = stage {
+                current_stage.unit_removed(stage.0);
+            }
             commands.entity(entity).despawn_recursive();
         };
     }
@@ -287,6 +575,9 @@ impl DamageAbsorber {
 pub struct Bullet {
     pub extents: Vec2,
     pub damage: f32,
+    pub damage_type: DamageType,
+    /// Tower that fired it, credited with the damage
+    pub source: Option<Entity>,
     hits: u32,
     already_hit: Vec<Entity>,
 }
@@ -295,10 +586,20 @@ impl Bullet {
         Self {
             extents,
             damage,
+            damage_type: DamageType::default(),
+            source: None,
             hits,
             already_hit: Vec::with_capacity(hits as usize),
         }
     }
+    pub fn with_damage_type(mut self, damage_type: DamageType) -> Self {
+        self.damage_type = damage_type;
+        self
+    }
+    pub fn with_source(mut self, source: Entity) -> Self {
+        self.source = Some(source);
+        self
+    }
     pub fn to_hitbox(&self) -> Hitbox {
         Hitbox::with_extents(self.extents)
     }
@@ -308,31 +609,98 @@ impl Default for Bullet {
         Self {
             extents: Default::default(),
             damage: Default::default(),
+            damage_type: Default::default(),
+            source: None,
             hits: 1,
             already_hit: vec![],
         }
     }
 }
 
+/// Adds to the damage total of the tower that did it, if it's still around.
+pub fn credit(dealt: &mut Query<&mut DamageDealt>, source: Option<Entity>, amount: f32) {
+    if let Some(mut total) = source.and_then(|source| dealt.get_mut(source).ok()) {
+        total.0 += amount;
+    }
+}
+
+/// Puts everything that can be hit into the `SpatialGrid`.
+fn index_damage_absorbers(
+    mut grid: ResMut<SpatialGrid>,
+    absorbers: Query<(Entity, &Transform, &DamageAbsorber)>,
+) {
+    grid.clear();
+    for (entity, transform, absorber) in absorbers.iter() {
+        grid.insert(entity, transform.translation.truncate(), absorber.extents);
+    }
+}
+
+/// Everything bullets can hit
+type BulletTargets<'w, 's> = Query<
+    'w,
+    's,
+    (
+        &'static mut Health,
+        &'static DamageAbsorber,
+        &'static Transform,
+        Entity,
+        Option<&'static mut StatusEffects>,
+        Option<&'static Armor>,
+    ),
+>;
+
+type Bullets<'w, 's> = Query<
+    'w,
+    's,
+    (
+        &'static mut Bullet,
+        &'static Transform,
+        Entity,
+        Option<&'static Explosive>,
+        Option<&'static Inflicts>,
+    ),
+>;
+
 pub fn absorb_bullets(
     mut commands: Commands,
-    mut targets: Query<(&mut Health, &DamageAbsorber, &Transform, Entity)>,
-    mut bullets: Query<(&mut Bullet, &Transform, Entity)>,
+    mut targets: BulletTargets,
+    mut bullets: Bullets,
+    mut explosions: EventWriter<ExplosionEvent>,
+    mut dealt: Query<&mut DamageDealt>,
+    grid: Res<SpatialGrid>,
 ) {
-    for (mut bullet, transform, bullet_entity) in bullets.iter_mut() {
-        let bullet_rect = bullet.to_hitbox().with_translation(transform);
+    let mut nearby = Vec::new();
+    for (mut bullet, bullet_transform, bullet_entity, explosive, inflicts) in bullets.iter_mut() {
+        let bullet_rect = bullet.to_hitbox().with_translation(bullet_transform);
         if bullet.hits == 0 {
             commands.entity(bullet_entity).despawn_recursive();
             break;
         };
-        for (mut target, damage_absorber, transform, target_entity) in targets.iter_mut() {
+        grid.query(bullet_transform.translation.truncate(), bullet.extents, &mut nearby);
+        for candidate in nearby.iter() {
+            let (mut target, damage_absorber, transform, target_entity, status, armor) =
+                match targets.get_mut(*candidate) {
+                    Ok(target) => target,
+                    Err(_) => continue,
+                };
             if bullet.hits == 0 {
                 commands.entity(bullet_entity).despawn_recursive();
                 break;
             };
             let target_rect = damage_absorber.to_hitbox().with_translation(transform);
             if bullet_rect.touches(&target_rect) && !bullet.already_hit.contains(&target_entity) {
-                target.damage(bullet.damage);
+                if let Some(explosive) = explosive {
+                    // Explosive bullets deal all their damage through the blast
+                    explosions.send(explosive.explosion(bullet_transform, &bullet, inflicts));
+                    bullet.hits = 0;
+                    commands.entity(bullet_entity).despawn_recursive();
+                    break;
+                }
+                let lost = target.damage(mitigated(bullet.damage, bullet.damage_type, armor));
+                credit(&mut dealt, bullet.source, lost);
+                if let (Some(mut status), Some(inflicts)) = (status, inflicts) {
+                    status.apply_all(&inflicts.0);
+                }
                 bullet.hits = bullet.hits.saturating_sub(1);
                 bullet.already_hit.push(target_entity);
             }
@@ -340,6 +708,139 @@ pub fn absorb_bullets(
     }
 }
 
+#[derive(Debug, Clone, Component, Reflect)]
+/// Steers a bullet towards a unit, picking the nearest one if its target is gone
+pub struct Homing {
+    pub target: Option<Entity>,
+    /// Radians per second
+    pub turn_rate: f32,
+}
+impl Homing {
+    pub fn new(target: Option<Entity>, turn_rate: f32) -> Self {
+        Self { target, turn_rate }
+    }
+}
+
+pub fn steer_homing(
+    mut bullets: Query<(&mut Homing, &mut Velocity, &Transform)>,
+    targets: Query<(Entity, &Transform), With<AiUnit>>,
+    clock: Res<SimulationClock>,
+) {
+    for (mut homing, mut velocity, transform) in bullets.iter_mut() {
+        let position = transform.translation.truncate();
+        let mut goal = homing.target.and_then(|target| targets.get(target).ok());
+        if goal.is_none() {
+            // Target died or escaped, chase whatever's closest. With nothing left the bullet
+            // just flies straight until its lifespan runs out.
+            goal = targets.iter().reduce(|x, y| {
+                if x.1.translation.truncate().distance_squared(position)
+                    > y.1.translation.truncate().distance_squared(position)
+                {
+                    y
+                } else {
+                    x
+                }
+            });
+            homing.target = goal.map(|(entity, _)| entity);
+        }
+        if let Some((_, goal)) = goal {
+            let heading = velocity.velocity.truncate();
+            let wanted = goal.translation.truncate() - position;
+            if heading == Vec2::ZERO || wanted == Vec2::ZERO {
+                continue;
+            }
+            let max_turn = homing.turn_rate * clock.delta_seconds();
+            let angle = heading.angle_between(wanted).clamp(-max_turn, max_turn);
+            velocity.velocity = Quat::from_rotation_z(angle) * velocity.velocity;
+        }
+    }
+}
+
+#[derive(Debug, Clone, Component, Reflect)]
+/// Makes a bullet blow up when it hits something or runs out of time
+pub struct Explosive {
+    pub radius: f32,
+}
+impl Explosive {
+    pub fn new(radius: f32) -> Self {
+        Self { radius }
+    }
+    fn explosion(&self, transform: &Transform, bullet: &Bullet, inflicts: Option<&Inflicts>) -> ExplosionEvent {
+        ExplosionEvent {
+            position: transform.translation.truncate(),
+            radius: self.radius,
+            damage: bullet.damage,
+            damage_type: bullet.damage_type,
+            source: bullet.source,
+            effects: inflicts.map(|inflicts| inflicts.0.clone()).unwrap_or_default(),
+        }
+    }
+}
+
+/// Damage everything within `radius` of `position`, `damage` at the centre falling off to nothing at the edge
+pub struct ExplosionEvent {
+    pub position: Vec2,
+    pub radius: f32,
+    pub damage: f32,
+    pub damage_type: DamageType,
+    pub source: Option<Entity>,
+    /// Status effects applied to everything caught in the blast
+    pub effects: Vec<StatusEffect>,
+}
+
+#[derive(Debug, Clone, Component)]
+/// Visual for an explosion, fades out over its lifespan
+pub struct ExplosionEffect;
+
+fn explode(
+    mut commands: Commands,
+    mut events: EventReader<ExplosionEvent>,
+    mut targets: Query<(&mut Health, &Transform, Option<&mut StatusEffects>, Option<&Armor>)>,
+    mut dealt: Query<&mut DamageDealt>,
+    grid: Res<SpatialGrid>,
+    asset_server: Res<AssetServer>,
+) {
+    let mut nearby = Vec::new();
+    for explosion in events.iter() {
+        grid.query_radius(explosion.position, explosion.radius, &mut nearby);
+        for candidate in nearby.iter() {
+            let (mut health, transform, status, armor) = match targets.get_mut(*candidate) {
+                Ok(target) => target,
+                Err(_) => continue,
+            };
+            let distance = transform.translation.truncate().distance(explosion.position);
+            if distance < explosion.radius {
+                let damage = explosion.damage * (1.0 - distance / explosion.radius);
+                let lost = health.damage(mitigated(damage, explosion.damage_type, armor));
+                credit(&mut dealt, explosion.source, lost);
+                if let Some(mut status) = status {
+                    status.apply_all(&explosion.effects);
+                }
+            }
+        }
+        commands
+            .spawn_bundle(SpriteBundle {
+                sprite: Sprite {
+                    color: Color::rgba(1.0, 0.5, 0.0, 0.6),
+                    custom_size: Some(Vec2::splat(explosion.radius * 2.0)),
+                    ..Default::default()
+                },
+                transform: Transform::from_translation(explosion.position.extend(5.0)),
+                texture: asset_server.load("circle.png"),
+                ..Default::default()
+            })
+            .insert(Lifespan::new(0.25))
+            .insert(ExplosionEffect)
+            .insert(DespawnOnExit(AppState::Playing));
+    }
+}
+
+fn fade_explosions(mut effects: Query<(&mut Sprite, &Lifespan), With<ExplosionEffect>>) {
+    for (mut sprite, lifespan) in effects.iter_mut() {
+        sprite.color.set_a(0.6 * (1.0 - lifespan.percent()));
+    }
+}
+
 pub fn rotate_bullets(
     mut bullets: Query<(&mut Transform, &Velocity), With<Bullet>>,
 ) {
@@ -354,6 +855,7 @@ pub struct StructureRect {
     pub extents: Vec2,
 }
 impl StructureRect {
+    #[allow(dead_code)]
     pub fn new(x: f32, y: f32) -> Self {
         Self {
             extents: Vec2::new(x, y),
@@ -384,4 +886,92 @@ impl Gold {
 
 #[derive(Debug, Clone, Component, Reflect, Default)]
 /// How many enemies can finish the course before the player loses.
-pub struct Lives(pub u32);
\ No newline at end of file
+pub struct Lives(pub u32);
+
+/// Gold and lives every new game starts with
+#[derive(Debug, Clone, Copy)]
+pub struct StartingResources {
+    pub gold: u32,
+    pub lives: u32,
+}
+impl Default for StartingResources {
+    fn default() -> Self {
+        Self {
+            gold: 100,
+            lives: 100,
+        }
+    }
+}
+
+/// Puts everything this plugin keeps track of back how it was at the start of a game.
+fn reset_resources(
+    starting: Res<StartingResources>,
+    mut gold: ResMut<Gold>,
+    mut lives: ResMut<Lives>,
+    mut over_ui: ResMut<CursorOverUi>,
+    mut grid: ResMut<SpatialGrid>,
+    mut explosions: ResMut<Events<ExplosionEvent>>,
+) {
+    *gold = Gold(starting.gold);
+    *lives = Lives(starting.lives);
+    *over_ui = CursorOverUi::default();
+    *grid = SpatialGrid::default();
+    explosions.clear();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn intercept_still_target() {
+        let target = Vec2::new(100.0, 50.0);
+        assert_eq!(intercept(Vec2::ZERO, target, Vec2::ZERO, 300.0), Some(target));
+    }
+
+    #[test]
+    fn intercept_meets_moving_target() {
+        let (source, target) = (Vec2::ZERO, Vec2::new(200.0, 0.0));
+        let (velocity, speed) = (Vec2::new(0.0, 100.0), 300.0);
+        let point = intercept(source, target, velocity, speed).unwrap();
+        // The bullet and the target get there at the same time
+        let bullet_time = point.distance(source) / speed;
+        let target_time = point.distance(target) / velocity.length();
+        assert!((bullet_time - target_time).abs() < 1e-4);
+        assert!(point.y > 0.0);
+    }
+
+    #[test]
+    fn intercept_gives_up_on_faster_targets() {
+        let velocity = Vec2::new(500.0, 0.0);
+        assert_eq!(intercept(Vec2::ZERO, Vec2::new(100.0, 0.0), velocity, 300.0), None);
+    }
+
+    #[test]
+    fn armor_stops_flat_physical_damage() {
+        let armor = Armor::new(2.0, Resistances::default());
+        assert_eq!(armor.reduce(5.0, DamageType::Physical), 3.0);
+        // Only physical hits are blunted by flat armor
+        assert_eq!(armor.reduce(5.0, DamageType::Explosive), 5.0);
+    }
+
+    #[test]
+    fn resistances_scale_their_damage_type() {
+        let resistances = Resistances {
+            piercing: 0.5,
+            magic: 2.0,
+            ..Default::default()
+        };
+        let armor = Armor::new(0.0, resistances);
+        assert_eq!(armor.reduce(4.0, DamageType::Piercing), 2.0);
+        // Resistances past 1 are clamped, leaving the minimum
+        assert_eq!(armor.reduce(4.0, DamageType::Magic), 4.0 * Armor::MIN_DAMAGE);
+    }
+
+    #[test]
+    fn armor_always_lets_something_through() {
+        let armor = Armor::new(100.0, Resistances::default());
+        assert_eq!(armor.reduce(5.0, DamageType::Physical), 5.0 * Armor::MIN_DAMAGE);
+        assert_eq!(mitigated(5.0, DamageType::Physical, None), 5.0);
+    }
+}
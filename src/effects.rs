@@ -0,0 +1,230 @@
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use crate::{
+    components::Health,
+    simulation::{SimulationAppExt, SimulationClock, SimulationSystem},
+};
+
+pub struct EffectsPlugin;
+
+impl Plugin for EffectsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_simulation_system(SimulationSystem::Status, tick_status_effects)
+            .add_system(tint_affected_units);
+    }
+}
+
+/// Poison is the only effect that stacks, up to this many at once
+const MAX_POISON_STACKS: usize = 5;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Reflect, Deserialize)]
+pub enum StatusKind {
+    /// Multiplies movement speed by `strength`
+    Slow,
+    /// Deals `strength` damage per second through armor, stacks
+    Poison,
+    /// Deals `strength` damage per second through armor, the strongest burn wins
+    Burn,
+    /// Stops the unit moving
+    Stun,
+}
+impl StatusKind {
+    fn tint(self) -> Color {
+        match self {
+            StatusKind::Slow => Color::rgb(0.4, 0.7, 1.0),
+            StatusKind::Poison => Color::rgb(0.3, 0.9, 0.2),
+            StatusKind::Burn => Color::rgb(1.0, 0.4, 0.0),
+            StatusKind::Stun => Color::rgb(1.0, 1.0, 0.6),
+        }
+    }
+}
+
+/// An effect a bullet applies to whatever it hits, as written in tower files.
+#[derive(Clone, Copy, Debug, Reflect, Deserialize)]
+pub struct StatusEffect {
+    pub kind: StatusKind,
+    /// Seconds
+    pub duration: f32,
+    #[serde(default)]
+    pub strength: f32,
+}
+
+#[derive(Clone, Debug)]
+struct ActiveEffect {
+    kind: StatusKind,
+    strength: f32,
+    timer: Timer,
+}
+impl ActiveEffect {
+    fn new(effect: &StatusEffect) -> Self {
+        Self {
+            kind: effect.kind,
+            strength: effect.strength,
+            timer: Timer::from_seconds(effect.duration, false),
+        }
+    }
+    fn remaining(&self) -> f32 {
+        self.timer.duration().as_secs_f32() - self.timer.elapsed_secs()
+    }
+}
+
+#[derive(Clone, Debug, Component)]
+/// Timed effects currently on a unit, and the ones it shrugs off
+pub struct StatusEffects {
+    active: Vec<ActiveEffect>,
+    immune: Vec<StatusKind>,
+    /// Sprite colour without any tint
+    base_color: Color,
+}
+impl StatusEffects {
+    pub fn new(base_color: Color, immune: Vec<StatusKind>) -> Self {
+        Self {
+            active: Vec::new(),
+            immune,
+            base_color,
+        }
+    }
+    /// Adds an effect. Poison stacks, everything else refreshes the existing effect,
+    /// keeping whichever is stronger and lasts longer.
+    pub fn apply(&mut self, effect: &StatusEffect) {
+        if self.immune.contains(&effect.kind) {
+            return;
+        }
+        let new = ActiveEffect::new(effect);
+        if effect.kind == StatusKind::Poison {
+            let stacks = self.active.iter().filter(|active| active.kind == StatusKind::Poison).count();
+            if stacks < MAX_POISON_STACKS {
+                self.active.push(new);
+                return;
+            }
+            // Full up, refresh the stack closest to running out instead
+            if let Some(oldest) = self
+                .active
+                .iter_mut()
+                .filter(|active| active.kind == StatusKind::Poison)
+                .min_by(|a, b| a.remaining().total_cmp(&b.remaining()))
+            {
+                *oldest = new;
+            }
+            return;
+        }
+        match self.active.iter_mut().find(|active| active.kind == effect.kind) {
+            Some(existing) => {
+                existing.strength = match effect.kind {
+                    StatusKind::Slow => existing.strength.min(effect.strength),
+                    _ => existing.strength.max(effect.strength),
+                };
+                if new.remaining() > existing.remaining() {
+                    existing.timer = new.timer;
+                }
+            }
+            None => self.active.push(new),
+        }
+    }
+    pub fn apply_all(&mut self, effects: &[StatusEffect]) {
+        for effect in effects {
+            self.apply(effect);
+        }
+    }
+    pub fn has(&self, kind: StatusKind) -> bool {
+        self.active.iter().any(|active| active.kind == kind)
+    }
+    /// What to multiply the unit's speed by
+    pub fn speed_multiplier(&self) -> f32 {
+        if self.has(StatusKind::Stun) {
+            return 0.0;
+        }
+        self.active
+            .iter()
+            .filter(|active| active.kind == StatusKind::Slow)
+            .fold(1.0, |speed, slow| speed * slow.strength.clamp(0.0, 1.0))
+    }
+}
+
+#[derive(Clone, Debug, Default, Component)]
+/// Status effects a bullet applies to everything it damages
+pub struct Inflicts(pub Vec<StatusEffect>);
+
+fn tick_status_effects(mut units: Query<(&mut StatusEffects, &mut Health)>, clock: Res<SimulationClock>) {
+    for (mut effects, mut health) in units.iter_mut() {
+        let mut damage = 0.0;
+        for active in effects.active.iter_mut() {
+            active.timer.tick(clock.delta());
+            if matches!(active.kind, StatusKind::Poison | StatusKind::Burn) {
+                damage += active.strength * clock.delta_seconds();
+            }
+        }
+        if damage > 0.0 {
+            // Damage over time isn't credited to any tower
+            health.damage(damage);
+        }
+        effects.active.retain(|active| !active.timer.finished());
+    }
+}
+
+/// Blends the colour of the most recent effect into the unit's sprite.
+fn tint_affected_units(mut units: Query<(&StatusEffects, &mut Sprite), Changed<StatusEffects>>) {
+    for (effects, mut sprite) in units.iter_mut() {
+        let base = effects.base_color;
+        sprite.color = match effects.active.last() {
+            Some(active) => {
+                let tint = active.kind.tint();
+                Color::rgba(
+                    (base.r() + tint.r()) / 2.0,
+                    (base.g() + tint.g()) / 2.0,
+                    (base.b() + tint.b()) / 2.0,
+                    base.a(),
+                )
+            }
+            None => base,
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn effect(kind: StatusKind, duration: f32, strength: f32) -> StatusEffect {
+        StatusEffect {
+            kind,
+            duration,
+            strength,
+        }
+    }
+
+    #[test]
+    fn poison_stacks_up_to_the_limit() {
+        let mut effects = StatusEffects::new(Color::WHITE, Vec::new());
+        for _ in 0..MAX_POISON_STACKS + 2 {
+            effects.apply(&effect(StatusKind::Poison, 2.0, 1.0));
+        }
+        assert_eq!(effects.active.len(), MAX_POISON_STACKS);
+    }
+
+    #[test]
+    fn refresh_keeps_strongest_and_longest() {
+        let mut effects = StatusEffects::new(Color::WHITE, Vec::new());
+        effects.apply(&effect(StatusKind::Slow, 1.0, 0.5));
+        effects.apply(&effect(StatusKind::Slow, 3.0, 0.8));
+        effects.apply(&effect(StatusKind::Burn, 3.0, 1.0));
+        effects.apply(&effect(StatusKind::Burn, 1.0, 2.0));
+        assert_eq!(effects.active.len(), 2);
+        // Slows are stronger the lower they go
+        assert_eq!(effects.speed_multiplier(), 0.5);
+        for active in effects.active.iter() {
+            assert_eq!(active.remaining(), 3.0);
+        }
+        let burn = effects.active.iter().find(|active| active.kind == StatusKind::Burn).unwrap();
+        assert_eq!(burn.strength, 2.0);
+    }
+
+    #[test]
+    fn immune_units_shrug_effects_off() {
+        let mut effects = StatusEffects::new(Color::WHITE, vec![StatusKind::Stun]);
+        effects.apply(&effect(StatusKind::Stun, 1.0, 0.0));
+        assert!(!effects.has(StatusKind::Stun));
+        assert_eq!(effects.speed_multiplier(), 1.0);
+    }
+}
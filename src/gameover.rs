@@ -1,74 +1,110 @@
 use bevy::prelude::*;
 
-use crate::components::Lives;
+use crate::{
+    components::Lives,
+    menu::{spawn_screen, take_key, MenuButton},
+    stages::{CurrentStage, GameMode, Stages},
+    state::AppState,
+};
 
+/// Ends the game when the player runs out of lives or clears the campaign, and shows how it went.
 pub struct GameOverPlugin;
 
 impl Plugin for GameOverPlugin {
     fn build(&self, app: &mut App) {
-        app.insert_resource(GameOver(false))
-            .add_system(lose_system)
-            .add_system(game_over);
+        app.add_system_set(
+            SystemSet::on_update(AppState::Playing)
+                .with_system(lose_system)
+                .with_system(win_system),
+        )
+        .add_system_set(SystemSet::on_enter(AppState::GameOver).with_system(game_over))
+        .add_system_set(SystemSet::on_enter(AppState::Victory).with_system(victory))
+        .add_system_set(SystemSet::on_update(AppState::GameOver).with_system(end_screen_keys))
+        .add_system_set(SystemSet::on_update(AppState::Victory).with_system(end_screen_keys));
     }
 }
 
-pub struct GameOver(bool);
-
 fn lose_system(
-    mut game_over: ResMut<GameOver>,
+    mut state: ResMut<State<AppState>>,
     lives: Option<Res<Lives>>,
 ) {
     if let Some(lives) = lives {
         if lives.0 == 0 {
-            game_over.0 = true;
+            if let Err(error) = state.set(AppState::GameOver) {
+                warn!("Can't end the game: {:?}", error);
+            }
         }
     }
 }
 
+/// Losing the last life on the final stage is still a loss.
+fn win_system(
+    mut state: ResMut<State<AppState>>,
+    lives: Res<Lives>,
+    current_stage: Res<CurrentStage>,
+    stages: Res<Stages>,
+    mode: Res<GameMode>,
+) {
+    if lives.0 > 0 && current_stage.campaign_won(&stages, *mode) {
+        if let Err(error) = state.set(AppState::Victory) {
+            warn!("Can't end the game: {:?}", error);
+        }
+    }
+}
+
+/// Lives left and stages cleared, for the end screens
+fn summary(lives: &Lives, current_stage: &CurrentStage) -> String {
+    format!(
+        "Stages cleared: {}\nLives left: {}",
+        current_stage.cleared, lives.0
+    )
+}
+
 fn game_over(
     mut commands: Commands,
-    entities: Query<Entity>,
-    mut game_over: ResMut<GameOver>,
     asset_server: Res<AssetServer>,
+    lives: Res<Lives>,
+    current_stage: Res<CurrentStage>,
 ) {
-    if game_over.0 {
-        for entity in entities.iter() {
-            commands.entity(entity).despawn_recursive();
-        }
-        let font = asset_server.load("fonts/NotoSans-Regular.ttf");
-        commands.spawn_bundle(UiCameraBundle::default());
-        commands
-            .spawn_bundle(NodeBundle {
-                style: Style {
-                    size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
-                    position_type: PositionType::Absolute,
-                    justify_content: JustifyContent::Center,
-                    align_items: AlignItems::Center,
-                    ..Default::default()
-                },
-                color: Color::NONE.into(),
-                ..Default::default()
-            })
-            .with_children(|parent| {
-                parent.spawn_bundle(TextBundle {
-                    style: Style {
-                        ..Default::default()
-                    },
-                    text: Text::with_section(
-                        "GAME OVER\nYou have lost.",
-                        TextStyle {
-                            font: font.clone(),
-                            font_size: 50.0,
-                            color: Color::WHITE,
-                        },
-                        TextAlignment {
-                            vertical: VerticalAlign::Center,
-                            horizontal: HorizontalAlign::Center,
-                        },
-                    ),
-                    ..Default::default()
-                });
-            });
-        game_over.0 = false;
+    let font = asset_server.load("fonts/NotoSans-Regular.ttf");
+    spawn_screen(
+        &mut commands,
+        font,
+        AppState::GameOver,
+        Color::rgba(0.0, 0.0, 0.0, 0.6),
+        "GAME OVER\nYou have lost.",
+        &summary(&lives, &current_stage),
+        &[MenuButton::Restart, MenuButton::MainMenu],
+    );
+}
+
+fn victory(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    lives: Res<Lives>,
+    current_stage: Res<CurrentStage>,
+) {
+    let font = asset_server.load("fonts/NotoSans-Regular.ttf");
+    spawn_screen(
+        &mut commands,
+        font,
+        AppState::Victory,
+        Color::rgba(0.0, 0.0, 0.0, 0.6),
+        "VICTORY\nEvery stage cleared.",
+        &summary(&lives, &current_stage),
+        &[MenuButton::Restart, MenuButton::MainMenu],
+    );
+}
+
+/// R plays again, Enter goes back to the main menu.
+fn end_screen_keys(
+    mut input: ResMut<Input<KeyCode>>,
+    mut state: ResMut<State<AppState>>,
+    mut mode: ResMut<GameMode>,
+) {
+    if take_key(&mut input, KeyCode::R) {
+        MenuButton::Restart.press(&mut state, &mut mode);
+    } else if take_key(&mut input, KeyCode::Return) {
+        MenuButton::MainMenu.press(&mut state, &mut mode);
     }
 }
@@ -0,0 +1,273 @@
+use std::time::{Duration, Instant};
+
+use anyhow::Context;
+use bevy::{
+    app::{Events, ManualEventReader},
+    asset::AssetPlugin,
+    ecs::system::SystemState,
+    input::InputPlugin,
+    log::{Level, LogPlugin, LogSettings},
+    prelude::*,
+    window::WindowPlugin,
+};
+use serde::Deserialize;
+
+use crate::{
+    actions::ActionsPlugin,
+    beam::BeamPlugin,
+    build::{
+        manage::TowerType,
+        towerdata::{TowerDefinitions, TowerDefinitionsHandle},
+        BuildPlugin, TowerBuilder,
+    },
+    components::{AiUnit, ComponentsPlugin, DamageDealt, Gold, Lives, StartingResources},
+    effects::EffectsPlugin,
+    map::{MapPlugin, SelectedMap},
+    pathfinding::{NavPaths, NavigationPlugin},
+    simulation::{SimulationClock, SimulationPlugin},
+    state::AppState,
+    stages::{
+        unitdata::EnemyRegistry, CurrentStage, GameMode, StageCleared, StagePlugin, StartStage,
+        Stages,
+    },
+};
+
+/// Give up if the data files still haven't loaded after this long
+const LOAD_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A scripted game with no window, loaded from a RON file given to `--headless`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Scenario {
+    /// Map file relative to the assets folder, defaults to the normal map.
+    #[serde(default)]
+    pub map: Option<String>,
+    #[serde(default = "Scenario::default_gold")]
+    pub gold: u32,
+    #[serde(default = "Scenario::default_lives")]
+    pub lives: u32,
+    /// Stop once this many stages have been played.
+    pub stages: usize,
+    /// Play endless mode instead of the campaign, which stops once it's won.
+    #[serde(default)]
+    pub endless: bool,
+    /// Stop after this much game time, in seconds, whatever happens.
+    #[serde(default = "Scenario::default_time_limit")]
+    pub time_limit: f32,
+    #[serde(default)]
+    pub towers: Vec<Placement>,
+}
+impl Scenario {
+    fn default_gold() -> u32 {
+        100
+    }
+    fn default_lives() -> u32 {
+        100
+    }
+    fn default_time_limit() -> f32 {
+        1800.0
+    }
+}
+
+/// A tower bought before stage `stage` starts, going by the same rules as placing it in the game.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Placement {
+    pub tower: String,
+    pub position: Vec2,
+    #[serde(default)]
+    pub stage: usize,
+}
+
+/// What happened over a headless run
+#[derive(Debug, Default)]
+struct Report {
+    stages_played: usize,
+    ticks: u64,
+    seconds: f32,
+    lives_lost: u32,
+    /// Whether the campaign was cleared
+    won: bool,
+    /// Gold the player had when each stage started
+    gold_by_stage: Vec<u32>,
+    /// Stages cleared, with their reward and when they were cleared in seconds
+    cleared: Vec<(usize, u32, f32)>,
+    /// Towers that couldn't be placed, and why
+    rejected: Vec<String>,
+    /// Damage dealt by each tower, by id and position
+    damage: Vec<(String, Vec2, f32)>,
+}
+impl Report {
+    fn print(&self, scenario: &Scenario) {
+        println!(
+            "Played {} of {} stages in {:.1}s ({} ticks)",
+            self.stages_played, scenario.stages, self.seconds, self.ticks
+        );
+        println!("Lives lost: {} of {}", self.lives_lost, scenario.lives);
+        if self.won {
+            println!("Won the campaign");
+        }
+        println!("Gold at the start of each stage:");
+        for (stage, gold) in self.gold_by_stage.iter().enumerate() {
+            println!("  {:>3}: {}", stage, gold);
+        }
+        for (stage, reward, seconds) in self.cleared.iter() {
+            println!("Cleared stage {} at {:.1}s for {} gold", stage, seconds, reward);
+        }
+        for tower in self.rejected.iter() {
+            println!("Couldn't place {}", tower);
+        }
+        println!("Damage by tower:");
+        for (id, position, damage) in self.damage.iter() {
+            println!("  {:>8} at ({:.0}, {:.0}): {:.1}", id, position.x, position.y, damage);
+        }
+    }
+}
+
+/// Runs the scenario in `path` without a window and prints a report.
+pub fn run(path: &str) -> anyhow::Result<()> {
+    let text = std::fs::read_to_string(path).with_context(|| format!("couldn't read {}", path))?;
+    let scenario: Scenario = ron::from_str(&text).with_context(|| format!("couldn't parse {}", path))?;
+
+    let mut app = App::new();
+    app.insert_resource(LogSettings {
+        level: Level::WARN,
+        ..Default::default()
+    })
+    .insert_resource(WindowDescriptor::default())
+    .add_plugins(MinimalPlugins)
+    .add_plugin(LogPlugin)
+    .add_plugin(TransformPlugin)
+    .add_plugin(InputPlugin)
+    .add_plugin(WindowPlugin {
+        add_primary_window: false,
+        exit_on_close: false,
+    })
+    .add_plugin(AssetPlugin)
+    .add_asset::<Image>()
+    .insert_resource(
+        scenario
+            .map
+            .clone()
+            .map(SelectedMap)
+            .unwrap_or_default(),
+    )
+    .add_plugin(SimulationPlugin)
+    .insert_resource(SimulationClock::manual())
+    // Straight into the game, without the menus or the clock following real time
+    .add_state(AppState::Playing)
+    .add_plugin(ComponentsPlugin)
+    .add_plugin(EffectsPlugin)
+    .add_plugin(BeamPlugin)
+    .add_plugin(MapPlugin)
+    .add_plugin(NavigationPlugin)
+    .add_plugin(StagePlugin)
+    .add_plugin(BuildPlugin)
+    .add_plugin(ActionsPlugin)
+    .insert_resource(StartingResources {
+        gold: scenario.gold,
+        lives: scenario.lives,
+    })
+    .insert_resource(if scenario.endless {
+        GameMode::Endless
+    } else {
+        GameMode::Campaign
+    });
+
+    wait_for_data(&mut app)?;
+    let report = play(&mut app, &scenario);
+    report.print(&scenario);
+    Ok(())
+}
+
+/// Updates the app until the map and every data file it plays with have loaded.
+pub fn wait_for_data(app: &mut App) -> anyhow::Result<()> {
+    let start = Instant::now();
+    loop {
+        app.update();
+        let world = &app.world;
+        let towers = world
+            .get_resource::<TowerDefinitionsHandle>()
+            .zip(world.get_resource::<Assets<TowerDefinitions>>())
+            .is_some_and(|(handle, towers)| towers.get(&handle.0).is_some());
+        let loaded = towers
+            && world.get_resource::<EnemyRegistry>().is_some_and(|registry| !registry.is_empty())
+            && world.get_resource::<NavPaths>().is_some_and(|paths| !paths.is_empty())
+            && world.get_resource::<Stages>().is_some_and(|stages| !stages.is_empty());
+        if loaded {
+            return Ok(());
+        }
+        if start.elapsed() > LOAD_TIMEOUT {
+            anyhow::bail!("timed out loading the map and data files");
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    }
+}
+
+/// Places towers and sends each stage once the last one is cleared, until the scenario ends.
+fn play(app: &mut App, scenario: &Scenario) -> Report {
+    let mut report = Report::default();
+    let mut cleared = ManualEventReader::<StageCleared>::default();
+    loop {
+        let clock = app.world.get_resource::<SimulationClock>().unwrap();
+        let (ticks, seconds) = (clock.tick(), clock.seconds() as f32);
+        let lives = app.world.get_resource::<Lives>().map_or(0, |lives| lives.0);
+        let stage = app.world.get_resource::<CurrentStage>().map_or(0, |stage| stage.index);
+        let in_stage = app.world.get_resource::<CurrentStage>().is_some_and(|stage| stage.in_stage);
+        let field_clear = app.world.query::<&AiUnit>().iter(&app.world).next().is_none();
+        let won = lives > 0 && {
+            let world = &app.world;
+            let mode = *world.get_resource::<GameMode>().unwrap();
+            let stages = world.get_resource::<Stages>().unwrap();
+            world.get_resource::<CurrentStage>().unwrap().campaign_won(stages, mode)
+        };
+        if lives == 0 || won || seconds > scenario.time_limit || (stage >= scenario.stages && field_clear) {
+            report.stages_played = stage;
+            report.won = won;
+            report.ticks = ticks;
+            report.seconds = seconds;
+            report.lives_lost = scenario.lives.saturating_sub(lives);
+            break;
+        }
+        if !in_stage && field_clear {
+            place_towers(app, scenario, stage, &mut report);
+            report
+                .gold_by_stage
+                .push(app.world.get_resource::<Gold>().map_or(0, |gold| gold.0));
+            if let Some(mut start) = app.world.get_resource_mut::<Events<StartStage>>() {
+                start.send(StartStage);
+            }
+        }
+        // One tick per update, as fast as they'll run
+        app.world
+            .get_resource_mut::<SimulationClock>()
+            .unwrap()
+            .advance(SimulationClock::STEP);
+        app.update();
+        let seconds = app.world.get_resource::<SimulationClock>().unwrap().seconds() as f32;
+        let events = app.world.get_resource::<Events<StageCleared>>().unwrap();
+        for event in cleared.iter(events) {
+            report.cleared.push((event.stage, event.reward, seconds));
+        }
+    }
+    report.damage = app
+        .world
+        .query::<(&TowerType, &Transform, &DamageDealt)>()
+        .iter(&app.world)
+        .map(|(tower, transform, dealt)| (tower.0.clone(), transform.translation.truncate(), dealt.0))
+        .collect();
+    report
+}
+
+/// Places the scenario's towers for `stage` the way a player's clicks would be, in order.
+fn place_towers(app: &mut App, scenario: &Scenario, stage: usize, report: &mut Report) {
+    let mut state = SystemState::<(TowerBuilder, ResMut<Gold>)>::new(&mut app.world);
+    let (mut builder, mut gold) = state.get_mut(&mut app.world);
+    for placement in scenario.towers.iter().filter(|placement| placement.stage == stage) {
+        if let Err(error) = builder.place(&placement.tower, placement.position, &mut gold) {
+            report.rejected.push(format!(
+                "{} at ({}, {}) before stage {}: {}",
+                placement.tower, placement.position.x, placement.position.y, stage, error
+            ));
+        }
+    }
+    state.apply(&mut app.world);
+}
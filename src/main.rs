@@ -7,6 +7,15 @@ use crate::ui::*;
 use crate::stages::*;
 use crate::build::*;
 use crate::gameover::*;
+use crate::map::*;
+use crate::effects::*;
+use crate::beam::*;
+use crate::simulation::*;
+use crate::state::*;
+use crate::menu::*;
+use crate::save::*;
+use crate::actions::*;
+use crate::replay::*;
 
 mod components;
 mod background;
@@ -16,8 +25,38 @@ mod rectangle;
 mod stages;
 mod build;
 mod gameover;
+mod map;
+mod effects;
+mod beam;
+mod spatial;
+mod headless;
+mod simulation;
+mod state;
+mod menu;
+mod save;
+mod actions;
+mod replay;
+mod serde_helpers;
 
 fn main() {
+    if let Some(scenario) = headless_scenario() {
+        if let Err(error) = headless::run(&scenario) {
+            eprintln!("Headless run failed: {:#}", error);
+            std::process::exit(1);
+        }
+        return;
+    }
+    let replay = match replay_mode() {
+        Ok(replay) => replay,
+        Err(error) => {
+            eprintln!("Can't play the replay: {}", error);
+            std::process::exit(1);
+        }
+    };
+    let map = match &replay {
+        Replay::Playing { file, .. } => SelectedMap(file.map.clone()),
+        _ => selected_map(),
+    };
     println!("Hello, world!");
     App::new()
         .insert_resource(WindowDescriptor {
@@ -28,16 +67,26 @@ fn main() {
         })
         .insert_resource(ClearColor(Color::BLACK))
         .add_plugins(DefaultPlugins)
+        .insert_resource(map)
+        .insert_resource(replay)
+        .add_plugin(SimulationPlugin)
+        .add_plugin(StatePlugin)
+        .add_plugin(MenuPlugin)
         .add_plugin(ComponentsPlugin)
+        .add_plugin(EffectsPlugin)
+        .add_plugin(BeamPlugin)
+        .add_plugin(MapPlugin)
         .add_plugin(BackgroundPlugin)
         .add_plugin(NavigationPlugin)
         .add_plugin(StagePlugin)
         .add_plugin(UiPlugin)
         .add_plugin(BuildPlugin)
         .add_plugin(GameOverPlugin)
+        .add_plugin(SavePlugin)
+        .add_plugin(ActionsPlugin)
+        .add_plugin(ReplayPlugin)
         .add_startup_system(setup)
-        .add_startup_system(spawn_background)
-        .add_system(debug_keys)
+        .add_system_set(SystemSet::on_update(AppState::Playing).with_system(debug_keys))
         .run();
 }
 
@@ -48,6 +97,8 @@ fn setup(mut commands: Commands) {
             OrthographicCameraBundle::new_2d()
         )
         .insert(MainCamera);
+    // ui camera, shared by the menus and the game
+    commands.spawn_bundle(UiCameraBundle::default());
     commands.spawn()
         .insert_bundle(SpriteBundle {
             sprite: Sprite {
@@ -57,38 +108,191 @@ fn setup(mut commands: Commands) {
             },
             ..Default::default()
         });
-    commands.insert_resource(
-        pathfinding::navdata::map1()
-    );
-    commands.insert_resource(
-        Gold(100)
-    );
 }
 
-fn spawn_unit_at(commands: &mut Commands, translation: Vec2) {
-    commands.spawn()
-        .insert_bundle(unitdata::UnitBundle::standard())
-        .insert(Transform::from_translation(translation.extend(1.0)));
+/// Map chosen with `--map <path>`, or the default map.
+fn selected_map() -> SelectedMap {
+    let mut args = std::env::args().skip_while(|arg| arg != "--map").skip(1);
+    args.next().map(SelectedMap).unwrap_or_default()
+}
+
+/// Plays back the replay given with `--replay <path>`, or records every game to the file given
+/// with `--record <path>`. Without either, nothing is recorded.
+fn replay_mode() -> Result<Replay, ReplayError> {
+    let arg = |flag: &str| std::env::args().skip_while(|arg| arg != flag).nth(1);
+    if let Some(path) = arg("--replay") {
+        return Ok(Replay::play(ReplayFile::load(&path)?));
+    }
+    Ok(arg("--record").map_or(Replay::Off, Replay::record))
+}
+
+/// Scenario file given with `--headless <path>`, to run without a window.
+fn headless_scenario() -> Option<String> {
+    let mut args = std::env::args().skip_while(|arg| arg != "--headless").skip(1);
+    args.next()
 }
 
 fn debug_keys(
-    mut commands: Commands,
     input: Res<Input<KeyCode>>,
     cursor: Res<CursorPosition>,
-    mut gold: ResMut<Gold>,
-    mut stages: ResMut<CurrentStage>,
+    mut actions: ResMut<PlayerActions>,
 ) {
     if input.just_pressed(KeyCode::U) {
-        spawn_unit_at(&mut commands, cursor.0);
+        actions.push(PlayerAction::SpawnUnit {
+            enemy: "standard".to_string(),
+            position: cursor.0,
+        });
     }
     if input.just_pressed(KeyCode::Y) {
-        println!("Vec2::new({}, {}),", cursor.0.x, cursor.0.y);
+        println!("({}, {}),", cursor.0.x, cursor.0.y);
     }
     if input.just_pressed(KeyCode::N) || input.just_pressed(KeyCode::Space) {
-        stages.start_stage();
-        info!("Starting stage {}...", stages.index);
+        actions.push(PlayerAction::StartStage);
     }
     if input.pressed(KeyCode::G) {
-        gold.0 += 1;
+        actions.push(PlayerAction::AddGold(1));
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::{
+        app::Events,
+        asset::AssetPlugin,
+        input::{keyboard::KeyboardInput, ElementState, InputPlugin},
+        window::WindowPlugin,
+    };
+
+    use super::*;
+
+    /// The game as `main` puts it together, without a window or anything drawing it.
+    fn app() -> App {
+        let mut app = App::new();
+        app.insert_resource(WindowDescriptor::default())
+            .add_plugins(MinimalPlugins)
+            .add_plugin(TransformPlugin)
+            .add_plugin(InputPlugin)
+            .add_plugin(WindowPlugin {
+                add_primary_window: false,
+                exit_on_close: false,
+            })
+            .add_plugin(AssetPlugin)
+            .add_asset::<Image>()
+            .insert_resource(SelectedMap::default())
+            .add_plugin(SimulationPlugin)
+            .add_plugin(StatePlugin)
+            .add_plugin(MenuPlugin)
+            .add_plugin(ComponentsPlugin)
+            .add_plugin(EffectsPlugin)
+            .add_plugin(BeamPlugin)
+            .add_plugin(MapPlugin)
+            .add_plugin(BackgroundPlugin)
+            .add_plugin(NavigationPlugin)
+            .add_plugin(StagePlugin)
+            .add_plugin(UiPlugin)
+            .add_plugin(BuildPlugin)
+            .add_plugin(GameOverPlugin)
+            .add_plugin(SavePlugin)
+            .add_plugin(ActionsPlugin)
+            .add_plugin(ReplayPlugin);
+        headless::wait_for_data(&mut app).unwrap();
+        app
+    }
+
+    fn press(app: &mut App, key: KeyCode) {
+        for state in [ElementState::Pressed, ElementState::Released] {
+            app.world.get_resource_mut::<Events<KeyboardInput>>().unwrap().send(KeyboardInput {
+                scan_code: 0,
+                key_code: Some(key),
+                state,
+            });
+            app.update();
+        }
+        // Let the state change and the commands it queued run
+        app.update();
+    }
+
+    fn state(app: &App) -> AppState {
+        *app.world.get_resource::<State<AppState>>().unwrap().current()
+    }
+
+    fn play(app: &mut App, ticks: usize) {
+        for _ in 0..ticks {
+            app.world.get_resource_mut::<SimulationClock>().unwrap().advance(SimulationClock::STEP);
+            app.update();
+        }
+    }
+
+    /// Everything a new game should start from: resources, the components of each entity that
+    /// belongs to the game, and how many entities there are counting their children.
+    fn snapshot(app: &mut App) -> (u32, u32, String, Vec<Vec<String>>, u32) {
+        let world = &mut app.world;
+        let gold = world.get_resource::<Gold>().unwrap().0;
+        let lives = world.get_resource::<Lives>().unwrap().0;
+        let stage = format!(
+            "{:?} {:?} {:?}",
+            world.get_resource::<CurrentStage>().unwrap(),
+            world.get_resource::<AutoSend>().unwrap(),
+            world.get_resource::<GameSpeed>().unwrap(),
+        );
+        let entities: Vec<Entity> = world
+            .query_filtered::<Entity, With<DespawnOnExit>>()
+            .iter(world)
+            .collect();
+        let mut components: Vec<Vec<String>> = entities
+            .into_iter()
+            .map(|entity| {
+                let location = world.entities().get(entity).unwrap();
+                let archetype = &world.archetypes()[location.archetype_id];
+                let mut names: Vec<String> = archetype
+                    .components()
+                    .map(|id| world.components().get_info(id).unwrap().name().to_string())
+                    .collect();
+                names.sort();
+                names
+            })
+            .collect();
+        components.sort();
+        (gold, lives, stage, components, world.entities().len())
+    }
+
+    #[test]
+    fn restart_matches_a_fresh_start() {
+        let mut app = app();
+        press(&mut app, KeyCode::Return);
+        assert_eq!(state(&app), AppState::Playing);
+        let fresh = snapshot(&mut app);
+
+        let actions = |app: &mut App| {
+            let mut actions = app.world.get_resource_mut::<PlayerActions>().unwrap();
+            actions.push(PlayerAction::PlaceTower {
+                tower: "dart".to_string(),
+                position: Vec2::new(-300.0, 360.0),
+            });
+            actions.push(PlayerAction::ToggleAutoSend);
+            actions.push(PlayerAction::StartStage);
+        };
+        actions(&mut app);
+        play(&mut app, 300);
+        assert_ne!(snapshot(&mut app), fresh);
+
+        // Restart from the pause menu
+        press(&mut app, KeyCode::Escape);
+        assert_eq!(state(&app), AppState::Paused);
+        press(&mut app, KeyCode::R);
+        assert_eq!(state(&app), AppState::Playing);
+        assert_eq!(snapshot(&mut app), fresh);
+
+        // Restart from the game over screen
+        actions(&mut app);
+        play(&mut app, 300);
+        app.world.insert_resource(Lives(0));
+        // One update for the loss to be noticed, one for the game over screen to go up
+        app.update();
+        app.update();
+        assert_eq!(state(&app), AppState::GameOver);
+        press(&mut app, KeyCode::R);
+        assert_eq!(state(&app), AppState::Playing);
+        assert_eq!(snapshot(&mut app), fresh);
+    }
+}
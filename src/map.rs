@@ -0,0 +1,168 @@
+use bevy::{
+    asset::{AssetLoader, BoxedFuture, LoadContext, LoadedAsset},
+    prelude::*,
+    reflect::TypeUuid,
+};
+use serde::Deserialize;
+
+use crate::{
+    background::{spawn_background, Background},
+    pathfinding::{NavPath, NavPaths},
+    rectangle::Hitbox,
+    state::AppState,
+};
+
+pub struct MapPlugin;
+
+impl Plugin for MapPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_asset::<MapDefinition>()
+            .init_asset_loader::<MapDefinitionLoader>()
+            .init_resource::<SelectedMap>()
+            .init_resource::<NavPaths>()
+            .init_resource::<MapBounds>()
+            .init_resource::<NoBuildZones>()
+            .add_startup_system(load_map)
+            .add_system(apply_map)
+            .add_system_set(SystemSet::on_enter(AppState::Playing).with_system(spawn_map_background));
+    }
+}
+
+/// A playable map, loaded from a `.map.ron` file.
+#[derive(Debug, Clone, Deserialize, TypeUuid)]
+#[uuid = "9e3852cb-acf6-4801-b4e1-45897000a03b"]
+pub struct MapDefinition {
+    /// Path of the background image relative to the assets folder.
+    pub background: String,
+    pub bounds: MapBounds,
+    pub paths: Vec<NavPath>,
+    /// Polygons the player isn't allowed to build on, on top of the paths.
+    #[serde(default)]
+    pub no_build: Vec<Vec<Vec2>>,
+}
+
+#[derive(Default)]
+pub struct MapDefinitionLoader;
+
+impl AssetLoader for MapDefinitionLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), anyhow::Error>> {
+        Box::pin(async move {
+            let mut map = ron::de::from_bytes::<MapDefinition>(bytes)?;
+            if map.paths.is_empty() {
+                anyhow::bail!("map has no paths");
+            }
+            for path in map.paths.iter_mut() {
+                path.measure();
+            }
+            load_context.set_default_asset(LoadedAsset::new(map));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["map.ron"]
+    }
+}
+
+/// Map file to play, relative to the assets folder
+#[derive(Debug, Clone)]
+pub struct SelectedMap(pub String);
+impl Default for SelectedMap {
+    fn default() -> Self {
+        Self("maps/map1.map.ron".to_string())
+    }
+}
+
+/// Handle to the map being played
+pub struct MapHandle(pub Handle<MapDefinition>);
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+/// Playable area of the map. Towers can only be built inside it.
+pub struct MapBounds {
+    pub min: Vec2,
+    pub max: Vec2,
+}
+impl MapBounds {
+    pub fn contains(&self, point: Vec2) -> bool {
+        point.x > self.min.x && point.x < self.max.x
+            && point.y > self.min.y && point.y < self.max.y
+    }
+    pub fn clamp(&self, point: Vec2) -> Vec2 {
+        point.clamp(self.min, self.max)
+    }
+}
+impl Default for MapBounds {
+    fn default() -> Self {
+        Self {
+            min: Vec2::splat(-512.0),
+            max: Vec2::splat(512.0),
+        }
+    }
+}
+
+/// Polygons that block building
+#[derive(Debug, Clone, Default)]
+pub struct NoBuildZones(pub Vec<Vec<Vec2>>);
+impl NoBuildZones {
+    pub fn touches(&self, hitbox: &Hitbox) -> bool {
+        self.0.iter().any(|polygon| hitbox.touches_polygon(polygon))
+    }
+}
+
+fn load_map(mut commands: Commands, asset_server: Res<AssetServer>, selected: Res<SelectedMap>) {
+    info!("Loading map {}", selected.0);
+    commands.insert_resource(MapHandle(asset_server.load(selected.0.as_str())));
+}
+
+/// Sets up paths, bounds and, during a game, the background whenever the map is loaded or changed.
+fn apply_map(
+    mut commands: Commands,
+    mut events: EventReader<AssetEvent<MapDefinition>>,
+    maps: Res<Assets<MapDefinition>>,
+    current: Res<MapHandle>,
+    asset_server: Res<AssetServer>,
+    backgrounds: Query<Entity, With<Background>>,
+    state: Res<State<AppState>>,
+) {
+    for event in events.iter() {
+        let handle = match event {
+            AssetEvent::Created { handle } | AssetEvent::Modified { handle } => handle,
+            AssetEvent::Removed { .. } => continue,
+        };
+        if *handle != current.0 {
+            continue;
+        }
+        if let Some(map) = maps.get(handle) {
+            let names: Vec<&str> = map.paths.iter().map(|path| path.name.as_str()).collect();
+            info!("Map loaded with paths: {}", names.join(", "));
+            commands.insert_resource(NavPaths(map.paths.clone()));
+            commands.insert_resource(map.bounds);
+            commands.insert_resource(NoBuildZones(map.no_build.clone()));
+            let playing = *state.current() == AppState::Playing
+                || state.inactives().contains(&AppState::Playing);
+            if !playing {
+                continue;
+            }
+            for background in backgrounds.iter() {
+                commands.entity(background).despawn_recursive();
+            }
+            spawn_background(&mut commands, asset_server.load(map.background.as_str()));
+        }
+    }
+}
+
+/// Puts the background of an already loaded map back at the start of a game.
+fn spawn_map_background(
+    mut commands: Commands,
+    maps: Res<Assets<MapDefinition>>,
+    current: Res<MapHandle>,
+    asset_server: Res<AssetServer>,
+) {
+    if let Some(map) = maps.get(&current.0) {
+        spawn_background(&mut commands, asset_server.load(map.background.as_str()));
+    }
+}
@@ -0,0 +1,233 @@
+use bevy::prelude::*;
+
+use crate::{
+    stages::GameMode,
+    state::{AppState, DespawnOnExit},
+    ui::{
+        action_button, tower_text, ButtonInteractions, HOVERED_BUTTON, NORMAL_BUTTON,
+        PRESSED_BUTTON,
+    },
+};
+
+/// Main menu and pause screen, and the buttons and keys that move between states.
+pub struct MenuPlugin;
+
+impl Plugin for MenuPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(SystemSet::on_enter(AppState::MainMenu).with_system(spawn_main_menu))
+            .add_system_set(SystemSet::on_enter(AppState::Paused).with_system(spawn_pause_menu))
+            .add_system_set(SystemSet::on_update(AppState::MainMenu).with_system(main_menu_keys))
+            .add_system_set(SystemSet::on_update(AppState::Playing).with_system(playing_keys))
+            .add_system_set(SystemSet::on_update(AppState::Paused).with_system(pause_menu_keys))
+            .add_system(menu_buttons);
+    }
+}
+
+#[derive(Debug, Clone, Copy, Component)]
+pub enum MenuButton {
+    Play(GameMode),
+    Resume,
+    /// Throws away the game in progress and starts a new one
+    Restart,
+    MainMenu,
+}
+impl MenuButton {
+    fn label(self) -> &'static str {
+        match self {
+            MenuButton::Play(GameMode::Campaign) => "Campaign",
+            MenuButton::Play(GameMode::Endless) => "Endless",
+            MenuButton::Resume => "Resume",
+            MenuButton::Restart => "Restart",
+            MenuButton::MainMenu => "Main Menu",
+        }
+    }
+    /// Moves to the state this button leads to. Restarting keeps the mode of the last game.
+    pub fn press(self, state: &mut State<AppState>, mode: &mut GameMode) {
+        let result = match self {
+            MenuButton::Play(chosen) => {
+                *mode = chosen;
+                state.set(AppState::Playing)
+            }
+            MenuButton::Resume => state.pop(),
+            MenuButton::Restart => state.replace(AppState::Playing),
+            // Replace unwinds the pause screen as well as the game under it
+            MenuButton::MainMenu => state.replace(AppState::MainMenu),
+        };
+        if let Err(error) = result {
+            warn!("Can't go from {:?} with {:?}: {:?}", state.current(), self, error);
+        }
+    }
+}
+
+fn menu_buttons(
+    mut interaction_query: ButtonInteractions<MenuButton>,
+    mut state: ResMut<State<AppState>>,
+    mut mode: ResMut<GameMode>,
+) {
+    for (interaction, mut color, button) in interaction_query.iter_mut() {
+        match *interaction {
+            Interaction::Clicked => {
+                *color = PRESSED_BUTTON.into();
+                button.press(&mut state, &mut mode);
+            }
+            Interaction::Hovered => {
+                *color = HOVERED_BUTTON.into();
+            }
+            Interaction::None => {
+                *color = NORMAL_BUTTON.into();
+            }
+        }
+    }
+}
+
+/// Whether `key` was just pressed, using the press up if it was. A state change runs the new
+/// state's systems in the same frame, so a key left pressed would act again there, like Escape
+/// pausing and then straight away resuming.
+pub fn take_key(input: &mut Input<KeyCode>, key: KeyCode) -> bool {
+    let pressed = input.just_pressed(key);
+    if pressed {
+        input.reset(key);
+    }
+    pressed
+}
+
+/// Enter starts a campaign, E an endless game.
+fn main_menu_keys(
+    mut input: ResMut<Input<KeyCode>>,
+    mut state: ResMut<State<AppState>>,
+    mut mode: ResMut<GameMode>,
+) {
+    if take_key(&mut input, KeyCode::Return) {
+        MenuButton::Play(GameMode::Campaign).press(&mut state, &mut mode);
+    } else if take_key(&mut input, KeyCode::E) {
+        MenuButton::Play(GameMode::Endless).press(&mut state, &mut mode);
+    }
+}
+
+/// Escape pauses the game, leaving it underneath the pause screen.
+fn playing_keys(mut input: ResMut<Input<KeyCode>>, mut state: ResMut<State<AppState>>) {
+    if take_key(&mut input, KeyCode::Escape) {
+        if let Err(error) = state.push(AppState::Paused) {
+            warn!("Can't pause: {:?}", error);
+        }
+    }
+}
+
+/// Escape goes back to the game, R starts a new one.
+fn pause_menu_keys(
+    mut input: ResMut<Input<KeyCode>>,
+    mut state: ResMut<State<AppState>>,
+    mut mode: ResMut<GameMode>,
+) {
+    if take_key(&mut input, KeyCode::Escape) {
+        MenuButton::Resume.press(&mut state, &mut mode);
+    } else if take_key(&mut input, KeyCode::R) {
+        MenuButton::Restart.press(&mut state, &mut mode);
+    }
+}
+
+fn spawn_main_menu(mut commands: Commands, asset_server: Res<AssetServer>) {
+    let font = asset_server.load("fonts/NotoSans-Regular.ttf");
+    spawn_screen(
+        &mut commands,
+        font,
+        AppState::MainMenu,
+        Color::rgb(0.1, 0.1, 0.1),
+        "Bevy Tower Defence",
+        "",
+        &[MenuButton::Play(GameMode::Campaign), MenuButton::Play(GameMode::Endless)],
+    );
+}
+
+fn spawn_pause_menu(mut commands: Commands, asset_server: Res<AssetServer>) {
+    let font = asset_server.load("fonts/NotoSans-Regular.ttf");
+    spawn_screen(
+        &mut commands,
+        font,
+        AppState::Paused,
+        Color::rgba(0.0, 0.0, 0.0, 0.6),
+        "Paused",
+        "",
+        &[MenuButton::Resume, MenuButton::Restart, MenuButton::MainMenu],
+    );
+}
+
+/// Fills the screen with `background`, with a title, any `details` under it and a column of
+/// buttons in the middle. Everything is despawned when the app leaves `state`.
+pub fn spawn_screen(
+    commands: &mut Commands,
+    font: Handle<Font>,
+    state: AppState,
+    background: Color,
+    title: &str,
+    details: &str,
+    buttons: &[MenuButton],
+) {
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                position_type: PositionType::Absolute,
+                flex_direction: FlexDirection::ColumnReverse,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..Default::default()
+            },
+            color: background.into(),
+            ..Default::default()
+        })
+        .insert(DespawnOnExit(state))
+        .with_children(|parent| {
+            parent.spawn_bundle(TextBundle {
+                text: Text::with_section(
+                    title,
+                    TextStyle {
+                        font: font.clone(),
+                        font_size: 50.0,
+                        color: Color::WHITE,
+                    },
+                    TextAlignment {
+                        vertical: VerticalAlign::Center,
+                        horizontal: HorizontalAlign::Center,
+                    },
+                ),
+                ..Default::default()
+            });
+            if !details.is_empty() {
+                parent.spawn_bundle(TextBundle {
+                    style: Style {
+                        margin: Rect::all(Val::Px(10.0)),
+                        ..Default::default()
+                    },
+                    text: Text::with_section(
+                        details,
+                        TextStyle {
+                            font: font.clone(),
+                            font_size: 25.0,
+                            color: Color::rgb(0.9, 0.9, 0.9),
+                        },
+                        TextAlignment {
+                            vertical: VerticalAlign::Center,
+                            horizontal: HorizontalAlign::Center,
+                        },
+                    ),
+                    ..Default::default()
+                });
+            }
+            for button in buttons {
+                parent
+                    .spawn_bundle(action_button())
+                    .insert(*button)
+                    .insert(Style {
+                        size: Size::new(Val::Px(200.0), Val::Px(40.0)),
+                        margin: Rect::all(Val::Px(5.0)),
+                        justify_content: JustifyContent::Center,
+                        align_items: AlignItems::Center,
+                        ..Default::default()
+                    })
+                    .with_children(|parent| {
+                        parent.spawn_bundle(tower_text(button.label(), font.clone()));
+                    });
+            }
+        });
+}
@@ -1,60 +1,133 @@
 use std::slice::Iter;
 
 use bevy::prelude::*;
+use serde::Deserialize;
 
-use crate::components::{Velocity, Lives};
-
-pub mod navdata;
+use crate::{
+    components::{Health, Lives, Velocity},
+    effects::StatusEffects,
+    simulation::{SimulationAppExt, SimulationSystem},
+    stages::{CurrentStage, StageUnit},
+};
 
 pub struct NavigationPlugin;
 
 impl Plugin for NavigationPlugin {
     fn build(&self, app: &mut App) {
-        app.add_system(follow_path)
-            .add_system(end_path);
+        app.add_simulation_system(SimulationSystem::Steer, follow_path)
+            .add_simulation_system(SimulationSystem::Leak, end_path);
     }
 }
 
 
 /// A path of positions that will be traversed
-pub struct NavPath(Vec<Vec2>);
+#[derive(Debug, Clone, Deserialize)]
+pub struct NavPath {
+    pub name: String,
+    points: Vec<Vec2>,
+    /// Distance from each point to the end of the path, filled in by `measure`
+    #[serde(skip)]
+    remaining: Vec<f32>,
+}
 impl NavPath {
+    /// Works out how far each point is from the end of the path.
+    pub fn measure(&mut self) {
+        let mut total = 0.0;
+        self.remaining = vec![0.0; self.points.len()];
+        for i in (0..self.points.len().saturating_sub(1)).rev() {
+            total += self.points[i].distance(self.points[i + 1]);
+            self.remaining[i] = total;
+        }
+    }
     pub fn get(&self, index: usize) -> Option<Vec2> {
-        self.0.get(index).cloned()
+        self.points.get(index).cloned()
+    }
+    pub fn iter(&self) -> Iter<'_, Vec2> {
+        self.points.iter()
+    }
+}
+
+/// Every path on the current map
+#[derive(Debug, Clone, Default)]
+pub struct NavPaths(pub Vec<NavPath>);
+impl NavPaths {
+    pub fn get(&self, path: usize) -> Option<&NavPath> {
+        self.0.get(path)
+    }
+    /// Index of the path called `name`
+    pub fn find(&self, name: &str) -> Option<usize> {
+        self.0.iter().position(|path| path.name == name)
     }
-    pub fn iter(&self) -> Iter<Vec2> {
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+    pub fn iter(&self) -> Iter<'_, NavPath> {
         self.0.iter()
     }
 }
 
 #[derive(Clone, Debug, Component, Reflect, Default)]
-/// Component to keep track of which part of the path you need to visit
+/// Component to keep track of which path you're on, and which part of it you need to visit
 pub struct PathFollow {
+    path: usize,
     index: usize,
     speed: f32,
 }
 impl PathFollow {
     pub fn new(index: usize, speed: f32) -> PathFollow {
         PathFollow {
+            path: 0,
             index,
             speed,
         }
     }
+    pub fn on_path(self, path: usize) -> PathFollow {
+        PathFollow { path, ..self }
+    }
+    pub fn at_point(self, index: usize) -> PathFollow {
+        PathFollow { index, ..self }
+    }
+    /// Index of the path being followed
+    pub fn path(&self) -> usize {
+        self.path
+    }
+    /// Index of the point on the path being headed for
+    pub fn index(&self) -> usize {
+        self.index
+    }
     pub fn advance(&mut self) {
         self.index += 1;
     }
+    /// How far a unit at `position` still has to travel, used to find the unit closest to the exit.
+    pub fn distance_left(&self, position: Vec2, paths: &NavPaths) -> f32 {
+        let path = match paths.get(self.path) {
+            Some(path) => path,
+            None => return f32::MAX,
+        };
+        match (path.get(self.index), path.remaining.get(self.index)) {
+            (Some(goal), Some(remaining)) => goal.distance(position) + remaining,
+            _ => 0.0,
+        }
+    }
 }
 
 pub fn follow_path(
-    mut query: Query<(&mut Velocity, &Transform, &mut PathFollow)>,
-    path: Res<NavPath>,
+    mut query: Query<(&mut Velocity, &Transform, &mut PathFollow, Option<&StatusEffects>)>,
+    paths: Res<NavPaths>,
 ) {
-    for (mut velocity, transform, mut navigation) in query.iter_mut() {
-        if let Some(goal) = path.get(navigation.index) {
+    for (mut velocity, transform, mut navigation, status) in query.iter_mut() {
+        let goal = paths
+            .get(navigation.path)
+            .and_then(|path| path.get(navigation.index));
+        if let Some(goal) = goal {
             let position = transform.translation.truncate();
             if goal.distance_squared(position) > 100.0 {
                 let direction = (goal - position).normalize();
-                velocity.velocity = (direction * navigation.speed).extend(0.0);
+                let speed = navigation.speed * status.map_or(1.0, StatusEffects::speed_multiplier);
+                velocity.velocity = (direction * speed).extend(0.0);
             } else {
                 navigation.advance();
             }
@@ -64,13 +137,22 @@ pub fn follow_path(
 
 pub fn end_path(
     mut commands: Commands,
-    query: Query<(&PathFollow, Entity)>,
+    query: Query<(&PathFollow, Entity, Option<&Health>, Option<&StageUnit>)>,
     mut lives: ResMut<Lives>,
-    path: Res<NavPath>,
+    mut current_stage: ResMut<CurrentStage>,
+    paths: Res<NavPaths>,
 ) {
-    for (navigation, entity) in query.iter() {
-        if let None = path.get(navigation.index) {
+    for (navigation, entity, health, stage) in query.iter() {
+        let finished = paths
+            .get(navigation.path)
+            .is_some_and(|path| path.get(navigation.index).is_none());
+        // Units killed this tick are already on their way out through `despawn_dead`
+        let dead = health.is_some_and(Health::dead);
+        if finished && !dead {
             lives.0 = lives.0.saturating_sub(1);
+            if let Some(stage) = stage {
+                current_stage.unit_removed(stage.0);
+            }
             commands.entity(entity).despawn_recursive();
         }
     }
@@ -1,151 +0,0 @@
-use bevy::math::Vec2;
-
-use super::NavPath;
-
-
-
-pub fn map1() -> NavPath {
-    let vec = vec![
-        Vec2::new(-505.77466, 402.92487),
-        Vec2::new(-493.08096, 407.88513),
-        Vec2::new(-445.16275, 409.45404),
-        Vec2::new(-353.61172, 402.2148),
-        Vec2::new(-293.68973, 402.2148),
-        Vec2::new(-233.61256, 410.67896),
-        Vec2::new(-168.13702, 410.6425),
-        Vec2::new(-126.72386, 405.6134),
-        Vec2::new(-105.00127, 392.40155),
-        Vec2::new(-85.09359, 373.73947),
-        Vec2::new(-76.97808, 360.83273),
-        Vec2::new(-72.7656, 344.867),
-        Vec2::new(-64.29174, 318.54218),
-        Vec2::new(-71.556915, 299.54697),
-        Vec2::new(-80.38786, 276.67032),
-        Vec2::new(-91.841064, 254.72444),
-        Vec2::new(-117.30438, 238.212),
-        Vec2::new(-135.44815, 238.212),
-        Vec2::new(-156.80438, 239.93883),
-        Vec2::new(-174.28711, 238.40593),
-        Vec2::new(-208.06577, 228.04506),
-        Vec2::new(-254.27614, 226.31825),
-        Vec2::new(-289.39038, 226.31825),
-        Vec2::new(-330.01813, 226.31825),
-        Vec2::new(-356.9918, 231.47713),
-        Vec2::new(-367.00064, 211.22829),
-        Vec2::new(-384.22556, 169.91437),
-        Vec2::new(-392.8381, 123.63375),
-        Vec2::new(-401.42896, 90.867516),
-        Vec2::new(-404.36237, 48.72523),
-        Vec2::new(-399.18192, 16.737564),
-        Vec2::new(-399.18192, -25.479975),
-        Vec2::new(-394.0015, -71.70584),
-        Vec2::new(-387.73883, -89.65367),
-        Vec2::new(-377.4766, -133.79922),
-        Vec2::new(-380.9085, -180.29364),
-        Vec2::new(-391.2479, -219.47693),
-        Vec2::new(-392.953, -281.29626),
-        Vec2::new(-392.953, -321.77942),
-        Vec2::new(-394.6798, -370.00043),
-        Vec2::new(-391.3651, -385.545),
-        Vec2::new(-342.0137, -395.81146),
-        Vec2::new(-314.98483, -395.81146),
-        Vec2::new(-289.68466, -397.53827),
-        Vec2::new(-261.4903, -388.94742),
-        Vec2::new(-244.56703, -375.54797),
-        Vec2::new(-243.01265, -345.52985),
-        Vec2::new(-249.8984, -301.7073),
-        Vec2::new(-263.53836, -271.36035),
-        Vec2::new(-291.07925, -230.02635),
-        Vec2::new(-299.16486, -212.08495),
-        Vec2::new(-289.55566, -187.53172),
-        Vec2::new(-278.0902, -159.27234),
-        Vec2::new(-270.0219, -119.06503),
-        Vec2::new(-261.40936, -76.53004),
-        Vec2::new(-252.7753, -26.625317),
-        Vec2::new(-251.13954, 8.8252535),
-        Vec2::new(-241.0721, 39.395027),
-        Vec2::new(-214.55861, 38.197723),
-        Vec2::new(-149.65135, 55.42264),
-        Vec2::new(-96.8516, 58.912205),
-        Vec2::new(-66.763916, 57.185394),
-        Vec2::new(-56.92243, 54.048798),
-        Vec2::new(-29.567453, 25.764334),
-        Vec2::new(-17.479765, -49.821777),
-        Vec2::new(-17.479765, -71.213745),
-        Vec2::new(-17.457842, -119.39144),
-        Vec2::new(-19.184654, -170.8456),
-        Vec2::new(-27.797218, -220.83675),
-        Vec2::new(-43.22597, -258.35498),
-        Vec2::new(-43.22597, -308.08905),
-        Vec2::new(-9.097808, -342.48135),
-        Vec2::new(28.197302, -350.54037),
-        Vec2::new(64.142494, -358.8417),
-        Vec2::new(120.84612, -363.94373),
-        Vec2::new(162.58221, -360.4901),
-        Vec2::new(176.09023, -346.9821),
-        Vec2::new(174.3634, -308.52362),
-        Vec2::new(165.89966, -272.8497),
-        Vec2::new(159.17538, -232.53299),
-        Vec2::new(155.78499, -182.78499),
-        Vec2::new(157.51181, -127.84507),
-        Vec2::new(164.41905, -63.99621),
-        Vec2::new(176.37816, -19.35666),
-        Vec2::new(194.7694, 9.124263),
-        Vec2::new(234.14882, 43.47755),
-        Vec2::new(262.63406, 36.854134),
-        Vec2::new(289.16525, 19.177067),
-        Vec2::new(296.81998, 0.87447304),
-        Vec2::new(310.6345, -60.92696),
-        Vec2::new(298.5683, -108.73419),
-        Vec2::new(293.38785, -176.0799),
-        Vec2::new(270.9612, -215.334),
-        Vec2::new(264.2011, -241.57715),
-        Vec2::new(269.30142, -310.71555),
-        Vec2::new(317.58728, -348.6622),
-        Vec2::new(368.54132, -345.2086),
-        Vec2::new(430.42453, -350.203),
-        Vec2::new(454.7968, -334.83673),
-        Vec2::new(470.17453, -300.46405),
-        Vec2::new(487.07968, -257.79016),
-        Vec2::new(500.6952, -230.53342),
-        Vec2::new(504.17032, -188.2537),
-        Vec2::new(485.23862, -136.88692),
-        Vec2::new(467.9705, -76.55639),
-        Vec2::new(466.24368, -47.98451),
-        Vec2::new(461.06323, 3.5128584),
-        Vec2::new(469.7264, 81.349495),
-        Vec2::new(474.77783, 113.585686),
-        Vec2::new(454.2694, 150.59564),
-        Vec2::new(388.1425, 154.04927),
-        Vec2::new(347.3786, 152.48883),
-        Vec2::new(285.25674, 135.2207),
-        Vec2::new(146.65767, 144.38333),
-        Vec2::new(135.42116, 157.83707),
-        Vec2::new(116.65641, 176.31729),
-        Vec2::new(126.91863, 241.08505),
-        Vec2::new(160.79596, 261.69687),
-        Vec2::new(217.21332, 266.87732),
-        Vec2::new(279.2226, 265.2866),
-        Vec2::new(374.45068, 265.2866),
-        Vec2::new(399.15768, 265.2102),
-        Vec2::new(463.74747, 266.93704),
-        Vec2::new(477.24072, 282.16568),
-        Vec2::new(477.24072, 320.12985),
-        Vec2::new(462.09656, 360.8963),
-        Vec2::new(440.3689, 396.4315),
-        Vec2::new(410.75507, 403.06302),
-        Vec2::new(376.0388, 397.8826),
-        Vec2::new(316.64124, 399.7399),
-        Vec2::new(272.9199, 398.01306),
-        Vec2::new(236.39882, 394.52783),
-        Vec2::new(175.5038, 399.70828),
-        Vec2::new(139.82799, 404.70395),
-        Vec2::new(103.794266, 411.22333),
-        Vec2::new(99.3651, 440.55417),
-        Vec2::new(115.78668, 476.43845),
-        Vec2::new(114.05987, 486.44226),
-        Vec2::new(99.38069, 511.77414),
-        Vec2::new(88.251686, 558.8413),            
-    ];
-    NavPath(vec)
-}
@@ -7,9 +7,11 @@ pub struct Hitbox {
     end: Vec2,
 }
 impl Hitbox {
+    #[allow(dead_code)]
     pub fn new(x: f32, y: f32, x2: f32, y2: f32) -> Self {
         Self { start: Vec2::new(x, y), end: Vec2::new(x2, y2) }
     }
+    #[allow(dead_code)]
     pub fn from_wh(x: f32, y: f32, w: f32, h: f32) -> Self {
         Self::new(x, y, x + w, y + h)
     }
@@ -33,7 +35,9 @@ impl Hitbox {
     pub fn sy(&self) -> f32 { self.start.y }
     pub fn ex(&self) -> f32 { self.end.x }
     pub fn ey(&self) -> f32 { self.end.y }
+    #[allow(dead_code)]
     pub fn width(&self) -> f32 { self.ex() - self.sx() }
+    #[allow(dead_code)]
     pub fn height(&self) -> f32 { self.ey() - self.sy() }
     pub fn touches(&self, other: &Hitbox) -> bool {
         self.ex() >= other.sx() &&
@@ -47,4 +51,54 @@ impl Hitbox {
         self.sx() <= other.x &&
         self.sy() <= other.y
     }
+    pub fn corners(&self) -> [Vec2; 4] {
+        [
+            self.start,
+            Vec2::new(self.ex(), self.sy()),
+            self.end,
+            Vec2::new(self.sx(), self.ey()),
+        ]
+    }
+    /// Whether this hitbox overlaps a closed polygon given by its vertices.
+    pub fn touches_polygon(&self, polygon: &[Vec2]) -> bool {
+        if polygon.iter().any(|point| self.point_touches(point)) {
+            return true;
+        }
+        let corners = self.corners();
+        if corners.iter().any(|corner| polygon_contains(polygon, *corner)) {
+            return true;
+        }
+        polygon_edges(polygon).any(|(a, b)| {
+            polygon_edges(&corners).any(|(c, d)| segments_intersect(a, b, c, d))
+        })
+    }
+}
+
+fn polygon_edges(polygon: &[Vec2]) -> impl Iterator<Item = (Vec2, Vec2)> + '_ {
+    polygon
+        .iter()
+        .zip(polygon.iter().cycle().skip(1))
+        .map(|(a, b)| (*a, *b))
+}
+
+/// Even-odd rule point in polygon test
+pub fn polygon_contains(polygon: &[Vec2], point: Vec2) -> bool {
+    let mut inside = false;
+    for (a, b) in polygon_edges(polygon) {
+        if (a.y > point.y) != (b.y > point.y)
+            && point.x < (b.x - a.x) * (point.y - a.y) / (b.y - a.y) + a.x
+        {
+            inside = !inside;
+        }
+    }
+    inside
+}
+
+fn segments_intersect(a: Vec2, b: Vec2, c: Vec2, d: Vec2) -> bool {
+    let cross = |o: Vec2, p: Vec2, q: Vec2| (p - o).perp_dot(q - o);
+    let d1 = cross(c, d, a);
+    let d2 = cross(c, d, b);
+    let d3 = cross(a, b, c);
+    let d4 = cross(a, b, d);
+    (d1 > 0.0) != (d2 > 0.0) && (d3 > 0.0) != (d4 > 0.0)
 }
\ No newline at end of file
@@ -0,0 +1,311 @@
+use bevy::{app::AppExit, prelude::*};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{
+    actions::PlayerAction,
+    build::towerdata::LoadedTowers,
+    components::{Gold, Lives},
+    map::SelectedMap,
+    pathfinding::NavPaths,
+    simulation::SimulationClock,
+    stages::{unitdata::EnemyRegistry, CurrentStage, GameMode, Stages},
+    state::AppState,
+};
+
+/// Writes every game's actions to a replay file when asked to, or plays a replay file back
+/// instead of taking input. See `Replay`.
+pub struct ReplayPlugin;
+
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(SystemSet::on_enter(AppState::Playing).with_system(begin_replay))
+            .add_system_set(SystemSet::on_exit(AppState::Playing).with_system(save_recording))
+            .add_system_set(SystemSet::on_update(AppState::MainMenu).with_system(start_playback))
+            .add_system_set(SystemSet::on_update(AppState::Playing).with_system(follow_playback))
+            .add_system_to_stage(CoreStage::Last, save_recording_on_exit);
+    }
+}
+
+/// Bumped whenever `ReplayFile` or `PlayerAction` change in a way older replays can't be read with.
+pub const REPLAY_VERSION: u32 = 1;
+
+/// A game as the actions taken in it, tick by tick. Starting from the same map, mode and data
+/// files, playing the actions back gives the same game.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ReplayFile {
+    pub version: u32,
+    pub map: String,
+    pub mode: GameMode,
+    /// Actions with the tick they were carried out on, in order
+    pub actions: Vec<(u64, PlayerAction)>,
+    /// How the game stood when the recording stopped
+    pub end: ReplayEnd,
+}
+
+/// What a playback has to match to count as the same game
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReplayEnd {
+    pub tick: u64,
+    pub gold: u32,
+    pub lives: u32,
+    pub stage: usize,
+    pub cleared: usize,
+}
+impl ReplayEnd {
+    pub fn new(clock: &SimulationClock, gold: &Gold, lives: &Lives, current_stage: &CurrentStage) -> Self {
+        Self {
+            tick: clock.tick(),
+            gold: gold.0,
+            lives: lives.0,
+            stage: current_stage.index,
+            cleared: current_stage.cleared,
+        }
+    }
+}
+
+/// Just enough of a replay to check its version before reading the rest.
+#[derive(Deserialize)]
+struct ReplayHeader {
+    version: u32,
+}
+
+#[derive(Debug, Error)]
+pub enum ReplayError {
+    #[error("couldn't access the replay: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("couldn't read the replay: {0}")]
+    Parse(#[from] ron::Error),
+    #[error("the replay is from version {found}, but this build only reads version {REPLAY_VERSION}")]
+    IncompatibleVersion { found: u32 },
+}
+
+impl ReplayFile {
+    /// Reads a replay, rejecting it if it was written by an incompatible version.
+    pub fn load(path: &str) -> Result<Self, ReplayError> {
+        let text = std::fs::read_to_string(path)?;
+        let header: ReplayHeader = ron::from_str(&text)?;
+        if header.version != REPLAY_VERSION {
+            return Err(ReplayError::IncompatibleVersion {
+                found: header.version,
+            });
+        }
+        Ok(ron::from_str(&text)?)
+    }
+    pub fn save(&self, path: &str) -> Result<(), ReplayError> {
+        let text = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::new())?;
+        Ok(std::fs::write(path, text)?)
+    }
+}
+
+/// Whether games are being recorded or a replay played back
+#[derive(Debug, Default)]
+pub enum Replay {
+    /// Actions are carried out without being kept
+    #[default]
+    Off,
+    /// Each game's actions are kept and written to `path` when it ends, replacing the last game.
+    /// `file` is empty between games, and after a load ends a game's recording early.
+    Recording {
+        path: String,
+        file: Option<ReplayFile>,
+    },
+    /// The replay's actions are carried out and the player's are ignored. The game starts by
+    /// itself and the clock holds once it reaches the end of the recording.
+    Playing {
+        file: ReplayFile,
+        /// Index of the next action to carry out
+        next: usize,
+        started: bool,
+        finished: bool,
+    },
+}
+
+impl Replay {
+    pub fn record(path: String) -> Self {
+        Self::Recording { path, file: None }
+    }
+    pub fn play(file: ReplayFile) -> Self {
+        Self::Playing {
+            file,
+            next: 0,
+            started: false,
+            finished: false,
+        }
+    }
+    /// The actions to carry out this tick, given the ones the player queued since the last.
+    pub fn actions_for(&mut self, tick: u64, queued: Vec<PlayerAction>) -> Vec<PlayerAction> {
+        match self {
+            Self::Off => queued,
+            Self::Recording { file, .. } => {
+                if let Some(file) = file {
+                    file.actions.extend(queued.iter().map(|action| (tick, action.clone())));
+                }
+                queued
+            }
+            Self::Playing { file, next, .. } => {
+                let mut actions = Vec::new();
+                while let Some((at, action)) = file.actions.get(*next) {
+                    if *at > tick {
+                        break;
+                    }
+                    if *at == tick {
+                        actions.push(action.clone());
+                    }
+                    *next += 1;
+                }
+                actions
+            }
+        }
+    }
+    /// Writes out the game's recording as it stands at `end`, and records nothing more of it.
+    pub fn end_recording(&mut self, end: ReplayEnd) {
+        if let Self::Recording { path, file } = self {
+            if let Some(mut file) = file.take() {
+                file.end = end;
+                match file.save(path) {
+                    Ok(()) => info!("Recorded the game to {}", path),
+                    Err(error) => error!("Couldn't record the game: {}", error),
+                }
+            }
+        }
+    }
+}
+
+/// Starts a fresh recording, or rewinds the replay, for a new game.
+fn begin_replay(mut replay: ResMut<Replay>, map: Res<SelectedMap>, mode: Res<GameMode>) {
+    match &mut *replay {
+        Replay::Off => {}
+        Replay::Recording { file, .. } => {
+            *file = Some(ReplayFile {
+                version: REPLAY_VERSION,
+                map: map.0.clone(),
+                mode: *mode,
+                ..Default::default()
+            });
+        }
+        Replay::Playing { next, finished, .. } => {
+            *next = 0;
+            *finished = false;
+        }
+    }
+}
+
+fn save_recording(
+    mut replay: ResMut<Replay>,
+    clock: Res<SimulationClock>,
+    gold: Res<Gold>,
+    lives: Res<Lives>,
+    current_stage: Res<CurrentStage>,
+) {
+    replay.end_recording(ReplayEnd::new(&clock, &gold, &lives, &current_stage));
+}
+
+/// Closing the window mid-game doesn't leave `Playing`, so the recording is saved here instead.
+fn save_recording_on_exit(
+    mut exit: EventReader<AppExit>,
+    state: Res<State<AppState>>,
+    replay: ResMut<Replay>,
+    clock: Res<SimulationClock>,
+    gold: Res<Gold>,
+    lives: Res<Lives>,
+    current_stage: Res<CurrentStage>,
+) {
+    let in_game = *state.current() == AppState::Playing
+        || state.inactives().contains(&AppState::Playing);
+    if exit.iter().next().is_some() && in_game {
+        save_recording(replay, clock, gold, lives, current_stage);
+    }
+}
+
+/// Starts the replayed game once everything it needs has loaded.
+fn start_playback(
+    mut replay: ResMut<Replay>,
+    mut state: ResMut<State<AppState>>,
+    mut mode: ResMut<GameMode>,
+    towers: LoadedTowers,
+    registry: Res<EnemyRegistry>,
+    paths: Res<NavPaths>,
+    stages: Res<Stages>,
+) {
+    if let Replay::Playing { file, started, .. } = &mut *replay {
+        let loaded = towers.get().is_some()
+            && !registry.is_empty()
+            && !paths.is_empty()
+            && !stages.is_empty();
+        if *started || !loaded {
+            return;
+        }
+        *started = true;
+        *mode = file.mode;
+        if let Err(error) = state.set(AppState::Playing) {
+            warn!("Can't start the replay: {:?}", error);
+        }
+    }
+}
+
+/// Holds the clock at the end of the replay and says whether the game came out the same.
+fn follow_playback(
+    mut replay: ResMut<Replay>,
+    mut clock: ResMut<SimulationClock>,
+    gold: Res<Gold>,
+    lives: Res<Lives>,
+    current_stage: Res<CurrentStage>,
+) {
+    if let Replay::Playing { file, finished, .. } = &mut *replay {
+        clock.stop_at(Some(file.end.tick));
+        if *finished || clock.tick() < file.end.tick {
+            return;
+        }
+        *finished = true;
+        let end = ReplayEnd::new(&clock, &gold, &lives, &current_stage);
+        if end == file.end {
+            info!("Replay finished at tick {}, matching the recording", end.tick);
+        } else {
+            warn!(
+                "Replay finished at tick {} but went differently: expected {:?}, got {:?}",
+                end.tick, file.end, end
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_path(name: &str) -> String {
+        let path = std::env::temp_dir().join(format!("{}-{}.ron", name, std::process::id()));
+        path.to_string_lossy().into_owned()
+    }
+
+    #[test]
+    fn load_reads_back_a_saved_replay() {
+        let path = temp_path("replay-roundtrip");
+        let file = ReplayFile {
+            version: REPLAY_VERSION,
+            actions: vec![(3, PlayerAction::StartStage), (9, PlayerAction::AddGold(5))],
+            ..Default::default()
+        };
+        file.save(&path).unwrap();
+        let loaded = ReplayFile::load(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.unwrap().actions, file.actions);
+    }
+
+    #[test]
+    fn load_rejects_other_versions() {
+        let path = temp_path("replay-version");
+        let file = ReplayFile {
+            version: REPLAY_VERSION + 1,
+            ..Default::default()
+        };
+        file.save(&path).unwrap();
+        let loaded = ReplayFile::load(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert!(matches!(
+            loaded,
+            Err(ReplayError::IncompatibleVersion { found }) if found == REPLAY_VERSION + 1
+        ));
+    }
+}
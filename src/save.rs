@@ -0,0 +1,383 @@
+use std::marker::PhantomData;
+
+use bevy::{ecs::system::SystemParam, prelude::*};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{
+    build::{
+        manage::{SelectedTower, TowerType, UpgradeLevel},
+        spawn_upgraded_tower,
+        towerdata::{LoadedTowers, TowerDefinitions},
+    },
+    components::{AiUnit, Bullet, ExplosionEffect, Gold, Health, Lives, Targeting},
+    map::SelectedMap,
+    pathfinding::{NavPaths, PathFollow},
+    replay::{Replay, ReplayEnd},
+    simulation::SimulationClock,
+    stages::{
+        unitdata::{EnemyRegistry, EnemyType},
+        CurrentStage, GameMode, StageUnit, Stages,
+    },
+    state::{AppState, DespawnOnExit},
+};
+
+/// Saves the game in progress with F5 and loads it back with F9.
+pub struct SavePlugin;
+
+impl Plugin for SavePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(
+            SystemSet::on_update(AppState::Playing)
+                .with_system(save_game)
+                .with_system(load_game),
+        );
+    }
+}
+
+/// Where the game is saved, relative to the working directory
+const SAVE_PATH: &str = "quicksave.ron";
+
+/// Bumped whenever `SaveFile` changes in a way older saves can't be read with.
+pub const SAVE_VERSION: u32 = 2;
+
+/// Everything needed to pick a game back up. Status effects, bullets in flight and tower
+/// damage totals aren't kept.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SaveFile {
+    pub version: u32,
+    /// Map file the game was played on, a save only loads on the same map
+    pub map: String,
+    pub mode: GameMode,
+    pub gold: u32,
+    pub lives: u32,
+    pub stage: CurrentStage,
+    pub towers: Vec<SavedTower>,
+    pub enemies: Vec<SavedEnemy>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SavedTower {
+    /// Id in the tower definitions
+    pub tower: String,
+    pub position: Vec3,
+    /// Upgrade tiers bought
+    pub level: usize,
+    pub targeting: Targeting,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SavedEnemy {
+    /// Id in the enemy definitions
+    pub enemy: String,
+    pub position: Vec3,
+    pub health: f32,
+    pub path: usize,
+    /// Point on the path the unit is heading for
+    pub point: usize,
+    /// Stage the unit was sent in, if it was sent in one
+    pub stage: Option<usize>,
+}
+
+/// Just enough of a save to check its version before reading the rest.
+#[derive(Deserialize)]
+struct SaveHeader {
+    version: u32,
+}
+
+#[derive(Debug, Error)]
+pub enum SaveError {
+    #[error("couldn't access {SAVE_PATH}: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("couldn't read the save: {0}")]
+    Parse(#[from] ron::Error),
+    #[error("the save is from version {found}, but this build only reads version {SAVE_VERSION}")]
+    IncompatibleVersion { found: u32 },
+    #[error("the save is on map {found}, but this game is on {expected}")]
+    WrongMap { found: String, expected: String },
+    #[error("the save has a tower of unknown type \"{0}\"")]
+    UnknownTower(String),
+    #[error("the save has an enemy of unknown type \"{0}\"")]
+    UnknownEnemy(String),
+    #[error("the save has an enemy on path {0}, but the map only has {1}")]
+    UnknownPath(usize, usize),
+    #[error("the tower and enemy files haven't loaded yet")]
+    NotLoaded,
+}
+
+impl SaveFile {
+    /// Reads a save, rejecting it if it was written by an incompatible version.
+    pub fn from_ron(text: &str) -> Result<Self, SaveError> {
+        let header: SaveHeader = ron::from_str(text)?;
+        if header.version != SAVE_VERSION {
+            return Err(SaveError::IncompatibleVersion {
+                found: header.version,
+            });
+        }
+        Ok(ron::from_str(text)?)
+    }
+    pub fn to_ron(&self) -> Result<String, SaveError> {
+        Ok(ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::new())?)
+    }
+    /// Checks the save is on `map` and every tower, enemy and path in it exists in the loaded data.
+    fn validate(
+        &self,
+        map: &SelectedMap,
+        towers: &TowerDefinitions,
+        registry: &EnemyRegistry,
+        paths: &NavPaths,
+    ) -> Result<(), SaveError> {
+        if self.map != map.0 {
+            return Err(SaveError::WrongMap {
+                found: self.map.clone(),
+                expected: map.0.clone(),
+            });
+        }
+        if let Some(tower) = self.towers.iter().find(|tower| towers.get(&tower.tower).is_none()) {
+            return Err(SaveError::UnknownTower(tower.tower.clone()));
+        }
+        for enemy in self.enemies.iter() {
+            if !registry.contains(&enemy.enemy) {
+                return Err(SaveError::UnknownEnemy(enemy.enemy.clone()));
+            }
+            if enemy.path >= paths.len() {
+                return Err(SaveError::UnknownPath(enemy.path, paths.len()));
+            }
+        }
+        Ok(())
+    }
+}
+
+type TowersToSave<'w, 's> = Query<
+    'w,
+    's,
+    (&'static TowerType, &'static Transform, &'static UpgradeLevel, &'static Targeting),
+>;
+
+type EnemiesToSave<'w, 's> = Query<
+    'w,
+    's,
+    (
+        &'static EnemyType,
+        &'static Transform,
+        &'static Health,
+        &'static PathFollow,
+        Option<&'static StageUnit>,
+    ),
+    With<AiUnit>,
+>;
+
+/// Everything a load replaces
+type InPlay<'w, 's> = Query<
+    'w,
+    's,
+    Entity,
+    Or<(With<TowerType>, With<AiUnit>, With<Bullet>, With<ExplosionEffect>)>,
+>;
+
+/// Where the game stands, written by a save and overwritten by a load
+#[derive(SystemParam)]
+struct Progress<'w, 's> {
+    mode: ResMut<'w, GameMode>,
+    gold: ResMut<'w, Gold>,
+    lives: ResMut<'w, Lives>,
+    current_stage: ResMut<'w, CurrentStage>,
+    stages: ResMut<'w, Stages>,
+    selected: ResMut<'w, SelectedTower>,
+    #[system_param(ignore)]
+    marker: PhantomData<&'s ()>,
+}
+
+/// The loaded data a save is checked against and rebuilt from
+#[derive(SystemParam)]
+struct SaveData<'w, 's> {
+    map: Res<'w, SelectedMap>,
+    towers: LoadedTowers<'w, 's>,
+    registry: Res<'w, EnemyRegistry>,
+    paths: Res<'w, NavPaths>,
+    asset_server: Res<'w, AssetServer>,
+}
+
+fn save_game(
+    input: Res<Input<KeyCode>>,
+    map: Res<SelectedMap>,
+    progress: Progress,
+    towers: TowersToSave,
+    enemies: EnemiesToSave,
+) {
+    if !input.just_pressed(KeyCode::F5) {
+        return;
+    }
+    let save = SaveFile {
+        version: SAVE_VERSION,
+        map: map.0.clone(),
+        mode: *progress.mode,
+        gold: progress.gold.0,
+        lives: progress.lives.0,
+        stage: progress.current_stage.clone(),
+        towers: towers
+            .iter()
+            .map(|(tower, transform, level, targeting)| SavedTower {
+                tower: tower.0.clone(),
+                position: transform.translation,
+                level: level.0,
+                targeting: *targeting,
+            })
+            .collect(),
+        enemies: enemies
+            .iter()
+            .map(|(enemy, transform, health, follow, stage)| SavedEnemy {
+                enemy: enemy.0.clone(),
+                position: transform.translation,
+                health: health.health,
+                path: follow.path(),
+                point: follow.index(),
+                stage: stage.map(|stage| stage.0),
+            })
+            .collect(),
+    };
+    let result = save
+        .to_ron()
+        .and_then(|text| std::fs::write(SAVE_PATH, text).map_err(SaveError::from));
+    match result {
+        Ok(()) => info!("Saved to {}", SAVE_PATH),
+        Err(error) => error!("Couldn't save: {}", error),
+    }
+}
+
+fn load_game(
+    mut commands: Commands,
+    input: Res<Input<KeyCode>>,
+    mut progress: Progress,
+    data: SaveData,
+    in_play: InPlay,
+    mut replay: ResMut<Replay>,
+    clock: Res<SimulationClock>,
+) {
+    if !input.just_pressed(KeyCode::F9) {
+        return;
+    }
+    if matches!(*replay, Replay::Playing { .. }) {
+        warn!("Can't load while a replay is playing");
+        return;
+    }
+    let save = match read_save(&data) {
+        Ok(save) => save,
+        Err(error) => {
+            error!("Couldn't load: {}", error);
+            return;
+        }
+    };
+    // Playing back past a load wouldn't give the same game, so the recording stops here
+    replay.end_recording(ReplayEnd::new(&clock, &progress.gold, &progress.lives, &progress.current_stage));
+    for entity in in_play.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    *progress.mode = save.mode;
+    *progress.gold = Gold(save.gold);
+    *progress.lives = Lives(save.lives);
+    *progress.current_stage = save.stage;
+    *progress.selected = SelectedTower::default();
+    // Generated stages aren't saved, but come out the same when made again
+    let current_stage = &progress.current_stage;
+    let playing = current_stage.in_stage.then(|| current_stage.index);
+    let outstanding = current_stage.outstanding.iter().map(|outstanding| outstanding.stage);
+    if let Some(last) = outstanding.chain(playing).max() {
+        progress.stages.generate_to(last);
+    }
+    if let Some(definitions) = data.towers.get() {
+        for tower in save.towers.iter() {
+            if let Some(definition) = definitions.get(&tower.tower) {
+                let entity = spawn_upgraded_tower(
+                    &mut commands,
+                    definition,
+                    tower.position,
+                    tower.level,
+                    &data.asset_server,
+                );
+                commands.entity(entity).insert(tower.targeting);
+            }
+        }
+    }
+    for enemy in save.enemies.iter() {
+        if let Some(unit) = data.registry.build(&enemy.enemy) {
+            let mut unit = unit
+                .on_path(enemy.path)
+                .at_point(enemy.point)
+                .with_health(enemy.health)
+                .spawn(&mut commands);
+            unit.insert(Transform::from_translation(enemy.position))
+                .insert(DespawnOnExit(AppState::Playing));
+            if let Some(stage) = enemy.stage {
+                unit.insert(StageUnit(stage));
+            }
+        }
+    }
+    info!("Loaded {}", SAVE_PATH);
+}
+
+fn read_save(data: &SaveData) -> Result<SaveFile, SaveError> {
+    let towers = data.towers.get().ok_or(SaveError::NotLoaded)?;
+    if data.registry.is_empty() {
+        return Err(SaveError::NotLoaded);
+    }
+    let save = SaveFile::from_ron(&std::fs::read_to_string(SAVE_PATH)?)?;
+    save.validate(&data.map, towers, &data.registry, &data.paths)?;
+    Ok(save)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn save(version: u32, map: &str) -> SaveFile {
+        SaveFile {
+            version,
+            map: map.to_string(),
+            mode: GameMode::default(),
+            gold: 120,
+            lives: 80,
+            stage: CurrentStage::default(),
+            towers: Vec::new(),
+            enemies: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn reads_back_a_save() {
+        let text = save(SAVE_VERSION, "maps/map1.map.ron").to_ron().unwrap();
+        let loaded = SaveFile::from_ron(&text).unwrap();
+        assert_eq!((loaded.gold, loaded.lives), (120, 80));
+    }
+
+    #[test]
+    fn rejects_other_versions() {
+        let text = save(SAVE_VERSION + 1, "maps/map1.map.ron").to_ron().unwrap();
+        assert!(matches!(
+            SaveFile::from_ron(&text),
+            Err(SaveError::IncompatibleVersion { found }) if found == SAVE_VERSION + 1
+        ));
+        // The version is checked before anything else, so older layouts get the same error
+        assert!(matches!(
+            SaveFile::from_ron("(version: 0, gold: 3)"),
+            Err(SaveError::IncompatibleVersion { found: 0 })
+        ));
+    }
+
+    #[test]
+    fn rejects_other_maps() {
+        let towers = TowerDefinitions {
+            towers: Vec::new(),
+            sell_fraction: 0.75,
+        };
+        let (registry, paths) = (EnemyRegistry::default(), NavPaths::default());
+        let save = save(SAVE_VERSION, "maps/map1.map.ron");
+        let map = SelectedMap("maps/map1.map.ron".to_string());
+        assert!(save.validate(&map, &towers, &registry, &paths).is_ok());
+        let other = SelectedMap("maps/map1_twoway.map.ron".to_string());
+        assert!(matches!(
+            save.validate(&other, &towers, &registry, &paths),
+            Err(SaveError::WrongMap { .. })
+        ));
+    }
+}
@@ -0,0 +1,20 @@
+// `#[serde(with = ...)]` modules for bevy types that don't implement serde themselves.
+
+/// Serializes a `Timer` as its duration and elapsed time. Timers are always read back as repeating.
+pub mod timer {
+    use std::time::Duration;
+
+    use bevy::core::Timer;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(timer: &Timer, serializer: S) -> Result<S::Ok, S::Error> {
+        (timer.duration(), timer.elapsed()).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Timer, D::Error> {
+        let (duration, elapsed) = <(Duration, Duration)>::deserialize(deserializer)?;
+        let mut timer = Timer::new(duration, true);
+        timer.set_elapsed(elapsed);
+        Ok(timer)
+    }
+}
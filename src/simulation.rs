@@ -0,0 +1,233 @@
+use std::time::Duration;
+
+use bevy::{ecs::schedule::ShouldRun, prelude::*};
+
+/// Gameplay runs in fixed ticks in its own stage, after `CoreStage::Update`, so it plays out the
+/// same whatever the frame rate. Systems in it should read `SimulationClock` instead of `Time`,
+/// and are added with `add_simulation_system`.
+///
+/// This plugin has to be added before any plugin that adds simulation systems.
+pub struct SimulationPlugin;
+
+impl Plugin for SimulationPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SimulationClock>()
+            .init_resource::<GameSpeed>()
+            .add_stage_after(
+                CoreStage::Update,
+                SimulationStage,
+                SystemStage::single_threaded().with_run_criteria(run_ticks),
+            )
+            .add_system_to_stage(CoreStage::First, accumulate_time);
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, StageLabel)]
+pub struct SimulationStage;
+
+/// Phases of a tick, in the order they run. Bevy doesn't keep the order systems were added in,
+/// so without these the same inputs could play out differently from one run to the next.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, SystemLabel)]
+pub enum SimulationSystem {
+    /// Player actions queued since the last tick are recorded, or swapped for replayed ones,
+    /// and carried out
+    Input,
+    /// Stages are sent and towers upgraded, sold or retargeted as the player asked
+    Orders,
+    /// Sends out the units of the current stage
+    Spawn,
+    /// Points units along their path and homing bullets at their target
+    Steer,
+    /// Moves everything with a `Velocity`
+    Move,
+    /// Rebuilds the `SpatialGrid`, anything querying it should come after this
+    Index,
+    /// Towers pick their targets
+    Aim,
+    Shoot,
+    /// Bullets hit whatever they touch
+    Collide,
+    Beam,
+    /// Status effects tick down and deal their damage
+    Status,
+    /// Anything with a `Lifespan` runs out
+    Expire,
+    /// Explosions from bullets that hit or ran out go off. This comes after both so each
+    /// explosion is read the tick it was sent, however many ticks a frame runs.
+    Explode,
+    /// Dead units are paid out and removed
+    Cleanup,
+    /// Units at the end of their path cost a life
+    Leak,
+    /// Stages with no units left are paid out
+    Clear,
+}
+impl SimulationSystem {
+    const ORDER: [Self; 16] = [
+        Self::Input,
+        Self::Orders,
+        Self::Spawn,
+        Self::Steer,
+        Self::Move,
+        Self::Index,
+        Self::Aim,
+        Self::Shoot,
+        Self::Collide,
+        Self::Beam,
+        Self::Status,
+        Self::Expire,
+        Self::Explode,
+        Self::Cleanup,
+        Self::Leak,
+        Self::Clear,
+    ];
+    fn previous(self) -> Option<Self> {
+        let index = Self::ORDER.iter().position(|phase| *phase == self)?;
+        index.checked_sub(1).map(|index| Self::ORDER[index])
+    }
+}
+
+pub trait SimulationAppExt {
+    /// Adds a system to `SimulationStage` in the given phase of each tick.
+    fn add_simulation_system<Params>(
+        &mut self,
+        phase: SimulationSystem,
+        system: impl ParallelSystemDescriptorCoercion<Params>,
+    ) -> &mut Self;
+}
+impl SimulationAppExt for App {
+    fn add_simulation_system<Params>(
+        &mut self,
+        phase: SimulationSystem,
+        system: impl ParallelSystemDescriptorCoercion<Params>,
+    ) -> &mut Self {
+        let mut system = system.label(phase);
+        if let Some(previous) = phase.previous() {
+            system = system.after(previous);
+        }
+        self.add_system_to_stage(SimulationStage, system)
+    }
+}
+
+/// Ticks per second of game time
+pub const TICK_RATE: f64 = 60.0;
+/// Most real time a single frame can catch up on, so a long hitch doesn't snowball into a longer one
+const MAX_CATCH_UP: f64 = 0.25;
+
+/// How fast game time passes compared to real time. Only the simulation is affected,
+/// the UI keeps running while paused.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GameSpeed {
+    pub paused: bool,
+    /// One of `GameSpeed::SPEEDS`
+    pub multiplier: f64,
+}
+impl Default for GameSpeed {
+    fn default() -> Self {
+        Self {
+            paused: false,
+            multiplier: 1.0,
+        }
+    }
+}
+impl GameSpeed {
+    /// Speeds the player can pick from
+    pub const SPEEDS: [f64; 3] = [1.0, 2.0, 4.0];
+
+    /// Seconds of game time per real second
+    pub fn scale(&self) -> f64 {
+        if self.paused {
+            0.0
+        } else {
+            self.multiplier
+        }
+    }
+    /// Unpauses at the given speed.
+    pub fn set(&mut self, multiplier: f64) {
+        self.paused = false;
+        self.multiplier = multiplier;
+    }
+}
+
+/// Counts fixed simulation ticks, and the time still owed to the simulation.
+#[derive(Debug, Clone)]
+pub struct SimulationClock {
+    accumulator: f64,
+    tick: u64,
+    /// Whether the clock follows real time. When it doesn't, time has to be fed in with `advance`.
+    realtime: bool,
+    /// Tick the clock holds at, however much time is owed
+    stop_at: Option<u64>,
+}
+impl Default for SimulationClock {
+    fn default() -> Self {
+        Self {
+            accumulator: 0.0,
+            tick: 0,
+            realtime: true,
+            stop_at: None,
+        }
+    }
+}
+impl SimulationClock {
+    /// Length of one tick in seconds
+    pub const STEP: f64 = 1.0 / TICK_RATE;
+
+    /// A clock that only moves when told to, for running faster than real time.
+    pub fn manual() -> Self {
+        Self {
+            realtime: false,
+            ..Default::default()
+        }
+    }
+    /// Owes the simulation another `seconds` of game time.
+    pub fn advance(&mut self, seconds: f64) {
+        self.accumulator += seconds;
+    }
+    /// Starts or stops the clock following real time, dropping any time still owed when it stops.
+    pub fn set_running(&mut self, running: bool) {
+        self.realtime = running;
+        if !running {
+            self.accumulator = 0.0;
+        }
+    }
+    /// Holds the clock once `tick` ticks have run, or lets it run on with `None`.
+    pub fn stop_at(&mut self, tick: Option<u64>) {
+        self.stop_at = tick;
+    }
+    /// Ticks run so far
+    pub fn tick(&self) -> u64 {
+        self.tick
+    }
+    /// Game time simulated so far, in seconds
+    pub fn seconds(&self) -> f64 {
+        self.tick as f64 * Self::STEP
+    }
+    /// Length of a tick, in place of `Time::delta`
+    pub fn delta(&self) -> Duration {
+        Duration::from_secs_f64(Self::STEP)
+    }
+    /// Length of a tick in seconds, in place of `Time::delta_seconds`
+    pub fn delta_seconds(&self) -> f32 {
+        Self::STEP as f32
+    }
+}
+
+fn accumulate_time(mut clock: ResMut<SimulationClock>, speed: Res<GameSpeed>, time: Res<Time>) {
+    if clock.realtime {
+        let owed = clock.accumulator + time.delta_seconds_f64() * speed.scale();
+        clock.accumulator = owed.min(MAX_CATCH_UP * speed.multiplier);
+    }
+}
+
+/// Runs the simulation stage once for every whole tick owed.
+fn run_ticks(mut clock: ResMut<SimulationClock>) -> ShouldRun {
+    let held = clock.stop_at.is_some_and(|stop_at| clock.tick >= stop_at);
+    if clock.accumulator >= SimulationClock::STEP && !held {
+        clock.accumulator -= SimulationClock::STEP;
+        clock.tick += 1;
+        ShouldRun::YesAndCheckAgain
+    } else {
+        ShouldRun::No
+    }
+}
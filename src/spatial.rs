@@ -0,0 +1,109 @@
+// Only depends on bevy so benches/spatial.rs can include it directly.
+use bevy::{prelude::*, utils::HashMap};
+
+/// Uniform grid for finding entities near a point without checking every one of them.
+/// Each entity goes in the cell holding its centre, and queries grow by the biggest entity
+/// so they still find anything poking in from a neighbouring cell. The grid is rebuilt from
+/// scratch every frame, so there's no removal.
+#[derive(Debug, Clone)]
+pub struct SpatialGrid {
+    cell_size: f32,
+    cells: HashMap<(i32, i32), Vec<Entity>>,
+    /// Size of the biggest entity inserted since the last clear
+    max_extents: Vec2,
+}
+impl Default for SpatialGrid {
+    fn default() -> Self {
+        Self::new(64.0)
+    }
+}
+impl SpatialGrid {
+    pub fn new(cell_size: f32) -> Self {
+        Self {
+            cell_size,
+            cells: HashMap::default(),
+            max_extents: Vec2::ZERO,
+        }
+    }
+    /// Empties every cell, keeping their allocations for the next rebuild.
+    pub fn clear(&mut self) {
+        for cell in self.cells.values_mut() {
+            cell.clear();
+        }
+        self.max_extents = Vec2::ZERO;
+    }
+    fn cell(&self, point: Vec2) -> (i32, i32) {
+        let cell = (point / self.cell_size).floor();
+        (cell.x as i32, cell.y as i32)
+    }
+    /// Adds an entity covering the box `position ± extents / 2`.
+    pub fn insert(&mut self, entity: Entity, position: Vec2, extents: Vec2) {
+        self.max_extents = self.max_extents.max(extents);
+        let cell = self.cell(position);
+        self.cells.entry(cell).or_default().push(entity);
+    }
+    /// Collects every entity that might overlap the box `position ± extents / 2` into `found`.
+    /// These are only candidates, callers still need an exact check.
+    pub fn query(&self, position: Vec2, extents: Vec2, found: &mut Vec<Entity>) {
+        found.clear();
+        let reach = (extents + self.max_extents) / 2.0;
+        let (min, max) = (self.cell(position - reach), self.cell(position + reach));
+        for x in min.0..=max.0 {
+            for y in min.1..=max.1 {
+                if let Some(cell) = self.cells.get(&(x, y)) {
+                    found.extend_from_slice(cell);
+                }
+            }
+        }
+    }
+    /// Like `query`, for everything that might be within `radius` of `position`.
+    pub fn query_radius(&self, position: Vec2, radius: f32, found: &mut Vec<Entity>) {
+        self.query(position, Vec2::splat(radius * 2.0), found);
+    }
+}
+
+// Imports are inside each test because benches/spatial.rs builds this module without a test
+// harness, which drops the tests and would leave a shared import unused.
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn finds_near_entities_only() {
+        use super::*;
+
+        let mut grid = SpatialGrid::new(64.0);
+        let (near, far) = (Entity::from_raw(0), Entity::from_raw(1));
+        grid.insert(near, Vec2::new(10.0, 10.0), Vec2::splat(8.0));
+        grid.insert(far, Vec2::new(1000.0, -1000.0), Vec2::splat(8.0));
+        let mut found = Vec::new();
+        grid.query(Vec2::ZERO, Vec2::splat(16.0), &mut found);
+        assert_eq!(found, vec![near]);
+        grid.query_radius(Vec2::new(900.0, -900.0), 200.0, &mut found);
+        assert_eq!(found, vec![far]);
+    }
+
+    #[test]
+    fn finds_big_entities_from_neighbouring_cells() {
+        use super::*;
+
+        let mut grid = SpatialGrid::new(64.0);
+        let big = Entity::from_raw(0);
+        // Centred three cells away, but reaching right up to the query
+        grid.insert(big, Vec2::new(200.0, 0.0), Vec2::splat(300.0));
+        let mut found = Vec::new();
+        grid.query(Vec2::new(40.0, 0.0), Vec2::splat(8.0), &mut found);
+        assert_eq!(found, vec![big]);
+    }
+
+    #[test]
+    fn clear_forgets_everything() {
+        use super::*;
+
+        let mut grid = SpatialGrid::new(64.0);
+        grid.insert(Entity::from_raw(0), Vec2::ZERO, Vec2::splat(500.0));
+        grid.clear();
+        let mut found = vec![Entity::from_raw(7)];
+        grid.query(Vec2::ZERO, Vec2::splat(16.0), &mut found);
+        assert!(found.is_empty());
+        assert_eq!(grid.max_extents, Vec2::ZERO);
+    }
+}
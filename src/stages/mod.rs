@@ -1,42 +1,159 @@
 use std::time::Duration;
 
-use bevy::prelude::*;
+use bevy::{
+    app::Events,
+    ecs::system::{EntityCommands, SystemParam},
+    prelude::*,
+};
+use serde::{Deserialize, Serialize};
 
-use crate::{components::Gold, pathfinding::NavPath};
+use crate::{
+    components::Gold,
+    pathfinding::NavPaths,
+    simulation::{SimulationAppExt, SimulationClock, SimulationSystem},
+    state::{AppState, DespawnOnExit},
+};
 
-use self::unitdata::UnitBundle;
+use self::{
+    unitdata::{EnemyDefinitions, EnemyDefinitionsHandle, EnemyDefinitionsLoader, EnemyRegistry},
+    wavedata::{WaveDefinitions, WaveDefinitionsHandle, WaveDefinitionsLoader},
+};
 
 pub struct StagePlugin;
 
 impl Plugin for StagePlugin {
     fn build(&self, app: &mut App) {
-        app.init_resource::<Stages>()
+        app.add_asset::<WaveDefinitions>()
+            .add_asset::<EnemyDefinitions>()
+            .init_asset_loader::<WaveDefinitionsLoader>()
+            .init_asset_loader::<EnemyDefinitionsLoader>()
+            .init_resource::<Stages>()
             .init_resource::<CurrentStage>()
-            .add_system(play_stages);
+            .init_resource::<EnemyRegistry>()
+            .init_resource::<AutoSend>()
+            .init_resource::<GameMode>()
+            .add_event::<StartStage>()
+            .add_event::<StageCleared>()
+            .add_startup_system(load_wave_definitions)
+            .add_startup_system(load_enemy_definitions)
+            .add_system(refresh_enemy_registry)
+            .add_system(refresh_stages)
+            .add_system_set(SystemSet::on_enter(AppState::Playing).with_system(reset_stages))
+            .add_simulation_system(SimulationSystem::Orders, start_stages)
+            .add_simulation_system(SimulationSystem::Spawn, play_stages)
+            .add_simulation_system(SimulationSystem::Clear, clear_stages);
     }
 }
 
 pub mod unitdata;
+pub mod wavedata;
 
-#[derive(Debug)]
+/// Asks for the next stage to be sent, paying a bonus if it's sent before the countdown runs out.
+#[derive(Debug, Clone, Copy)]
+pub struct StartStage;
+
+/// Sent once every unit of a stage has been killed or leaked, after its reward is paid.
+#[derive(Debug, Clone, Copy)]
+pub struct StageCleared {
+    pub stage: usize,
+    pub reward: u32,
+}
+
+/// Whether the next stage is sent by itself when the countdown runs out
+#[derive(Debug, Default)]
+pub struct AutoSend(pub bool);
+
+/// How a game ends, picked from the main menu
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum GameMode {
+    /// Won by clearing the wave file's `campaign_length` stages
+    #[default]
+    Campaign,
+    /// Stages keep coming until the player runs out of lives
+    Endless,
+}
+
+#[derive(Debug, Clone, Copy, Component)]
+/// Stage a unit was sent in, so the stage knows when all of its units are gone
+pub struct StageUnit(pub usize);
+
+/// A stage that has started but still has units to spawn or on the field
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutstandingStage {
+    pub stage: usize,
+    /// Units spawned that haven't died or leaked yet
+    pub units: u32,
+    pub spawning: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CurrentStage {
     pub index: usize,
+    /// Whether the current stage is still spawning units
     pub in_stage: bool,
     pub spawn_data: StageSpawnData,
+    /// Seconds until the next stage is due, counting down between stages
+    pub countdown: Option<f32>,
+    /// Stages that haven't been cleared yet, oldest first
+    pub outstanding: Vec<OutstandingStage>,
+    /// How many stages have been cleared so far
+    pub cleared: usize,
 }
 impl CurrentStage {
     pub fn start_stage(&mut self) {
         if !self.in_stage {
             self.in_stage = true;
-            self.spawn_data.unit_group_index = 0;
-            self.spawn_data.spawn_timer.reset();
-            self.spawn_data.counter = 0;
+            self.countdown = None;
+            self.spawn_data.restart();
+            self.outstanding.push(OutstandingStage {
+                stage: self.index,
+                units: 0,
+                spawning: true,
+            });
         }
     }
-    pub fn finish_stage(&mut self) {
+    /// Stops spawning and moves on to the next stage, counting down `countdown` seconds to it
+    /// if there's a countdown yet.
+    pub fn finish_stage(&mut self, countdown: Option<f32>) {
         if self.in_stage {
             self.in_stage = false;
+            if let Some(outstanding) = self.outstanding_mut(self.index) {
+                outstanding.spawning = false;
+            }
             self.index += 1;
+            self.countdown = countdown;
+        }
+    }
+    /// Whether the next stage can be sent now. Nothing can be sent until the stages are built,
+    /// which waits for the wave, enemy and path data to load.
+    pub fn can_start(&self, stages: &Stages, mode: GameMode) -> bool {
+        !stages.is_empty()
+            && !self.in_stage
+            && (stages.overlap_waves || self.outstanding.is_empty())
+            && stages.has_stage(self.index, mode)
+    }
+    /// Whether every stage of the campaign has been sent and cleared
+    pub fn campaign_won(&self, stages: &Stages, mode: GameMode) -> bool {
+        mode == GameMode::Campaign
+            && !self.in_stage
+            && self.outstanding.is_empty()
+            && self.cleared >= stages.campaign_length
+    }
+    fn outstanding_mut(&mut self, stage: usize) -> Option<&mut OutstandingStage> {
+        self.outstanding.iter_mut().find(|outstanding| outstanding.stage == stage)
+    }
+    /// Counts a unit from `stage` as gone, whether it was killed or leaked.
+    pub fn unit_removed(&mut self, stage: usize) {
+        if let Some(outstanding) = self.outstanding_mut(stage) {
+            outstanding.units = outstanding.units.saturating_sub(1);
+        }
+    }
+    /// Gold paid for starting the next stage right now. Only auto-sent stages can be sent early,
+    /// without auto-send the countdown isn't waiting on anything.
+    pub fn early_bonus(&self, stages: &Stages, auto_send: &AutoSend) -> u32 {
+        match (self.in_stage, self.countdown) {
+            (false, Some(countdown)) if auto_send.0 => (countdown * stages.early_bonus).round() as u32,
+            _ => 0,
         }
     }
 }
@@ -49,129 +166,338 @@ impl Default for CurrentStage {
                 unit_group_index: 0,
                 spawn_timer: Timer::from_seconds(1.0, true),
                 counter: 0,
-            }
+                delay_elapsed: 0.0,
+                next_path: 0,
+            },
+            countdown: None,
+            outstanding: Vec::new(),
+            cleared: 0,
         }
     }
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct StageSpawnData {
     unit_group_index: usize,
+    #[serde(with = "crate::serde_helpers::timer")]
     spawn_timer: Timer,
     counter: usize,
+    /// Seconds waited so far before the current group starts spawning
+    delay_elapsed: f32,
+    /// Path the next unit without a fixed path spawns on
+    next_path: usize,
+}
+impl StageSpawnData {
+    /// Rewinds spawning to the start of the first group.
+    fn restart(&mut self) {
+        self.unit_group_index = 0;
+        self.spawn_timer.reset();
+        self.counter = 0;
+        self.delay_elapsed = 0.0;
+    }
+    /// Picks the path for the next unit of `group`, taking turns between paths if it has none.
+    fn choose_path(&mut self, group: &UnitGroup, paths: &NavPaths) -> usize {
+        match &group.path {
+            Some(name) => paths.find(name).unwrap_or_else(|| {
+                warn!("No path called \"{}\" on this map", name);
+                0
+            }),
+            None => {
+                let path = self.next_path % paths.len().max(1);
+                self.next_path = path + 1;
+                path
+            }
+        }
+    }
 }
 
-fn play_stages(
-    mut commands: Commands,
-    time: Res<Time>,
+fn load_wave_definitions(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(WaveDefinitionsHandle(
+        asset_server.load("data/default.waves.ron"),
+    ));
+}
+
+fn load_enemy_definitions(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(EnemyDefinitionsHandle(
+        asset_server.load("data/default.enemies.ron"),
+    ));
+}
+
+/// Rebuilds the enemy registry whenever the enemy file is loaded or changed.
+fn refresh_enemy_registry(
+    mut events: EventReader<AssetEvent<EnemyDefinitions>>,
+    definitions: Res<Assets<EnemyDefinitions>>,
+    current: Res<EnemyDefinitionsHandle>,
+    mut registry: ResMut<EnemyRegistry>,
+) {
+    for event in events.iter() {
+        let handle = match event {
+            AssetEvent::Created { handle } | AssetEvent::Modified { handle } => handle,
+            AssetEvent::Removed { .. } => continue,
+        };
+        if *handle != current.0 {
+            continue;
+        }
+        if let Some(definitions) = definitions.get(handle) {
+            *registry = EnemyRegistry::from_definitions(definitions);
+        }
+    }
+}
+
+/// Replaces the stage list whenever the wave file, enemy registry or map paths change.
+/// Waves referring to unknown enemies or paths are rejected, keeping the previous stages.
+fn refresh_stages(
+    mut events: EventReader<AssetEvent<WaveDefinitions>>,
+    definitions: Res<Assets<WaveDefinitions>>,
+    current: Res<WaveDefinitionsHandle>,
+    registry: Res<EnemyRegistry>,
+    paths: Res<NavPaths>,
     mut stages: ResMut<Stages>,
-    path: Res<NavPath>,
+) {
+    let waves_changed = events.iter().any(|event| match event {
+        AssetEvent::Created { handle } | AssetEvent::Modified { handle } => *handle == current.0,
+        AssetEvent::Removed { .. } => false,
+    });
+    if !(waves_changed || registry.is_changed() || paths.is_changed())
+        || registry.is_empty()
+        || paths.is_empty()
+    {
+        return;
+    }
+    if let Some(definitions) = definitions.get(&current.0) {
+        let valid = definitions
+            .validate_enemies(&registry)
+            .and_then(|_| definitions.validate_paths(&paths));
+        match valid {
+            Ok(()) => *stages = Stages::from_definitions(definitions),
+            Err(error) => error!("Invalid wave file: {}", error),
+        }
+    }
+}
+
+/// Rewinds to the first stage for a new game.
+fn reset_stages(
+    mut current_stage: ResMut<CurrentStage>,
+    mut stages: ResMut<Stages>,
+    mut auto_send: ResMut<AutoSend>,
+    mut start: ResMut<Events<StartStage>>,
+    mut cleared: ResMut<Events<StageCleared>>,
+) {
+    *current_stage = CurrentStage::default();
+    stages.drop_generated();
+    *auto_send = AutoSend::default();
+    start.clear();
+    cleared.clear();
+}
+
+fn start_stages(
+    mut events: EventReader<StartStage>,
     mut current_stage: ResMut<CurrentStage>,
+    stages: Res<Stages>,
+    mode: Res<GameMode>,
+    auto_send: Res<AutoSend>,
     mut gold: ResMut<Gold>,
 ) {
-    if current_stage.in_stage {
-        if let Some(stage) = stages.0.get(current_stage.index) {
+    for StartStage in events.iter() {
+        if !current_stage.can_start(&stages, *mode) {
+            continue;
+        }
+        let bonus = current_stage.early_bonus(&stages, &auto_send);
+        gold.0 += bonus;
+        current_stage.start_stage();
+        info!("Starting stage {} with an early bonus of {}...", current_stage.index, bonus);
+    }
+}
+
+/// Spawns enemies from the registry onto the map's paths
+#[derive(SystemParam)]
+pub struct UnitSpawner<'w, 's> {
+    commands: Commands<'w, 's>,
+    registry: Res<'w, EnemyRegistry>,
+    pub paths: Res<'w, NavPaths>,
+}
+impl<'w, 's> UnitSpawner<'w, 's> {
+    /// Spawns enemy `id` at `translation`, heading down `path`. Unknown enemies are skipped with a warning.
+    pub fn spawn(&mut self, id: &str, path: usize, translation: Vec2) -> Option<EntityCommands<'w, 's, '_>> {
+        let unit = match self.registry.build(id) {
+            Some(unit) => unit,
+            None => {
+                warn!("Can't spawn unknown enemy \"{}\"", id);
+                return None;
+            }
+        };
+        let mut unit = unit.on_path(path).spawn(&mut self.commands);
+        unit.insert(Transform::from_translation(translation.extend(1.0)))
+            .insert(DespawnOnExit(AppState::Playing));
+        Some(unit)
+    }
+}
+
+fn play_stages(
+    mut spawner: UnitSpawner,
+    clock: Res<SimulationClock>,
+    mut stages: ResMut<Stages>,
+    mut current_stage: ResMut<CurrentStage>,
+    auto_send: Res<AutoSend>,
+    mode: Res<GameMode>,
+) {
+    if !current_stage.in_stage {
+        let more = stages.has_stage(current_stage.index, *mode);
+        if let Some(countdown) = current_stage.countdown.as_mut() {
+            *countdown = (*countdown - clock.delta_seconds()).max(0.0);
+            if *countdown == 0.0 && auto_send.0 && more {
+                current_stage.start_stage();
+                info!("Sending stage {}...", current_stage.index);
+            }
+        }
+    } else {
+        if let Some(stage) = stages.stages.get(current_stage.index) {
             if let Some(units) = stage.units.get(current_stage.spawn_data.unit_group_index) {
+                if current_stage.spawn_data.delay_elapsed < units.delay {
+                    current_stage.spawn_data.delay_elapsed += clock.delta_seconds();
+                    return;
+                }
                 current_stage.spawn_data.spawn_timer.set_duration(Duration::from_secs_f32(units.secs_between_spawns));
-                current_stage.spawn_data.spawn_timer.tick(time.delta());
+                current_stage.spawn_data.spawn_timer.tick(clock.delta());
                 if current_stage.spawn_data.spawn_timer.just_finished() {
                     if current_stage.spawn_data.counter != units.count {
-                        println!("spawning unit {}", current_stage.spawn_data.counter);
-                        let translation = path.get(0).unwrap();
-                        commands.spawn_bundle(units.unit_data.clone())
-                            .insert(Transform::from_translation(translation.extend(1.0)));
+                        debug!("spawning unit {}", current_stage.spawn_data.counter);
+                        let path = current_stage.spawn_data.choose_path(units, &spawner.paths);
+                        let translation = spawner.paths.get(path).and_then(|path| path.get(0)).unwrap_or_default();
+                        let stage = current_stage.index;
+                        if let Some(mut unit) = spawner.spawn(&units.enemy, path, translation) {
+                            unit.insert(StageUnit(stage));
+                            if let Some(outstanding) = current_stage.outstanding_mut(stage) {
+                                outstanding.units += 1;
+                            }
+                        }
                         current_stage.spawn_data.counter += 1;
                     } else {
                         current_stage.spawn_data.counter = 0;
+                        current_stage.spawn_data.delay_elapsed = 0.0;
                         current_stage.spawn_data.unit_group_index += 1;
-                        println!("going to unit group {}", current_stage.spawn_data.unit_group_index);
+                        debug!("going to unit group {}", current_stage.spawn_data.unit_group_index);
                     }
                 }
             } else {
-                current_stage.finish_stage();
-                gold.0 += stage.reward.0;
-                println!("going to stage {}", current_stage.index);
+                let countdown = stages.overlap_waves.then(|| stages.send_delay);
+                current_stage.finish_stage(countdown);
+                debug!("going to stage {}", current_stage.index);
             }
+        } else if stages.has_stage(current_stage.index, *mode) {
+            let index = current_stage.index as u32;
+            stages.stages.push(StageData::scale_with_stage(index));
         } else {
-            stages.0.push(StageData::scale_with_stage(current_stage.index as u32));
+            info!("No stages left to play.");
+            let index = current_stage.index;
+            current_stage.in_stage = false;
+            current_stage.outstanding.retain(|outstanding| outstanding.stage != index);
         }
     }
 }
 
+/// Pays out stages once all their units are spawned and gone.
+fn clear_stages(
+    mut current_stage: ResMut<CurrentStage>,
+    stages: Res<Stages>,
+    mut gold: ResMut<Gold>,
+    mut cleared: EventWriter<StageCleared>,
+) {
+    let done = |outstanding: &OutstandingStage| !outstanding.spawning && outstanding.units == 0;
+    if !current_stage.outstanding.iter().any(done) {
+        return;
+    }
+    for outstanding in current_stage.outstanding.iter().filter(|outstanding| done(outstanding)) {
+        let reward = stages.stages.get(outstanding.stage).map_or(0, |stage| stage.reward.0);
+        gold.0 += reward;
+        cleared.send(StageCleared {
+            stage: outstanding.stage,
+            reward,
+        });
+        info!("Cleared stage {} for {} gold", outstanding.stage, reward);
+    }
+    let before = current_stage.outstanding.len();
+    current_stage.outstanding.retain(|outstanding| !done(outstanding));
+    current_stage.cleared += before - current_stage.outstanding.len();
+    if !stages.overlap_waves && current_stage.outstanding.is_empty() && !current_stage.in_stage {
+        current_stage.countdown = Some(stages.send_delay);
+    }
+}
+
 /// Resource to keep track of coming stages
-pub struct Stages(Vec<StageData>);
+pub struct Stages {
+    stages: Vec<StageData>,
+    /// How many of `stages` came from the wave file, the rest were generated
+    defined: usize,
+    /// Stages to clear to win a campaign
+    campaign_length: usize,
+    /// Seconds to wait between stages with auto-send on
+    send_delay: f32,
+    /// Gold per second of countdown left when a stage is sent early
+    early_bonus: f32,
+    /// Whether a stage can be sent before the last one is cleared
+    overlap_waves: bool,
+}
 impl Default for Stages {
     fn default() -> Self {
-        Self(
-            vec![
-                StageData::new(80,
-                    vec![
-                        UnitGroup::new(
-                            UnitBundle::standard(),
-                            20,
-                            0.5,
-                        ),
-                    ]
-                ),
-                StageData::new(100,
-                    vec![
-                        UnitGroup::new(
-                            UnitBundle::standard(),
-                            7,
-                            0.4,
-                        ),
-                        UnitGroup::new(
-                            UnitBundle::standard_tank(),
-                            3,
-                            0.5,
-                        ),
-                        UnitGroup::new(
-                            UnitBundle::standard(),
-                            7,
-                            0.4,
-                        ),
-                        UnitGroup::new(
-                            UnitBundle::standard_tank(),
-                            3,
-                            0.5,
-                        ),
-                    ]
-                ),
-                StageData::new(100,
-                    vec![
-                        UnitGroup::new(
-                            UnitBundle::standard_op(),
-                            99,
-                            0.01,
-                        ),
-                    ]
-                ),
-                StageData::new(100,
-                    vec![
-                        UnitGroup::new(
-                            UnitBundle::standard_tank(),
-                            10,
-                            0.4,
-                        ),
-                        UnitGroup::new(
-                            UnitBundle::standard(),
-                            5,
-                            0.4,
-                        ),
-                        UnitGroup::new(
-                            UnitBundle::standard_tank(),
-                            10,
-                            0.4,
-                        ),
-                        UnitGroup::new(
-                            UnitBundle::standard_fast(),
-                            5,
-                            0.5,
-                        ),
-                    ]
-                ),
-            ]
-        )
+        Self {
+            stages: Vec::new(),
+            defined: 0,
+            campaign_length: 0,
+            send_delay: WaveDefinitions::default_send_delay(),
+            early_bonus: WaveDefinitions::default_early_bonus(),
+            overlap_waves: false,
+        }
+    }
+}
+impl Stages {
+    pub fn is_empty(&self) -> bool {
+        self.stages.is_empty()
+    }
+    /// Whether stage `index` gets played in this mode. Stages past the wave file are generated
+    /// by `play_stages` when they're reached, not here.
+    pub fn has_stage(&self, index: usize, mode: GameMode) -> bool {
+        match mode {
+            GameMode::Campaign => index < self.campaign_length,
+            GameMode::Endless => true,
+        }
+    }
+    /// Generates stages up to and including `index`, for picking up a game part way through.
+    pub fn generate_to(&mut self, index: usize) {
+        while self.stages.len() <= index {
+            let stage = self.stages.len() as u32;
+            self.stages.push(StageData::scale_with_stage(stage));
+        }
+    }
+    /// Forgets stages generated past the end of the wave file.
+    pub fn drop_generated(&mut self) {
+        self.stages.truncate(self.defined);
+    }
+    pub fn from_definitions(definitions: &WaveDefinitions) -> Self {
+        let stages: Vec<StageData> = definitions
+            .stages
+            .iter()
+            .map(|stage| {
+                let units = stage
+                    .groups
+                    .iter()
+                    .map(|group| {
+                        UnitGroup::new(&group.enemy, group.count, group.interval)
+                            .with_delay(group.delay)
+                            .with_path(group.path.clone())
+                    })
+                    .collect();
+                StageData::new(stage.reward, units)
+            })
+            .collect();
+        Self {
+            defined: stages.len(),
+            stages,
+            campaign_length: definitions.campaign_length(),
+            send_delay: definitions.send_delay,
+            early_bonus: definitions.early_bonus,
+            overlap_waves: definitions.overlap_waves,
+        }
     }
 }
 
@@ -183,31 +509,34 @@ impl StageData {
     pub fn new(reward: u32, units: Vec<UnitGroup>) -> Self {
         Self { reward: Gold(reward), units }
     }
+    /// Generates stage `stage`, with more and faster units the later it is. Stage 0 is
+    /// generated like stage 1.
     pub fn scale_with_stage(stage: u32) -> Self {
+        let stage = stage.max(1);
         let reward = (100.0 * 1.01_f32.powi(stage as i32)) as u32;
         let units = vec![
             UnitGroup::new(
-                UnitBundle::standard(),
+                "standard",
                 10 + stage as usize,
                 1.0 / stage as f32,
             ),
             UnitGroup::new(
-                UnitBundle::standard_tank(),
+                "tank",
                 5 + (stage / 2) as usize,
                 2.0 / stage as f32,
             ),
             UnitGroup::new(
-                UnitBundle::standard_fast(),
+                "fast",
                 (stage / 3) as usize,
                 3.0 / stage as f32,
             ),
             UnitGroup::new(
-                UnitBundle::standard_large(),
+                "large",
                 (stage / 15) as usize,
                 10.0 / stage as f32,
             ),
             UnitGroup::new(
-                UnitBundle::standard_op(),
+                "op",
                 (stage / 21) as usize,
                 10.0 / stage as f32,
             ),
@@ -217,12 +546,42 @@ impl StageData {
 }
 
 pub struct UnitGroup {
-    unit_data: UnitBundle,
+    /// Enemy type, as named in wave files
+    enemy: String,
     count: usize,
     secs_between_spawns: f32,
+    delay: f32,
+    /// Map path to spawn on, or `None` to take turns between all of them
+    path: Option<String>,
 }
 impl UnitGroup {
-    pub fn new(unit_data: UnitBundle, count: usize, secs_between_spawns: f32) -> Self {
-        Self { unit_data, count, secs_between_spawns }
+    pub fn new(enemy: &str, count: usize, secs_between_spawns: f32) -> Self {
+        Self { enemy: enemy.to_string(), count, secs_between_spawns, delay: 0.0, path: None }
+    }
+    pub fn with_delay(self, delay: f32) -> Self {
+        Self { delay, ..self }
+    }
+    pub fn with_path(self, path: Option<String>) -> Self {
+        Self { path, ..self }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn generated_stages_spawn_at_finite_intervals() {
+        for stage in 0..3 {
+            let generated = StageData::scale_with_stage(stage);
+            assert!(generated.units.iter().all(|group| group.secs_between_spawns.is_finite()));
+        }
+    }
+
+    #[test]
+    fn cant_start_before_stages_load() {
+        let current_stage = CurrentStage::default();
+        assert!(!current_stage.can_start(&Stages::default(), GameMode::Campaign));
+        assert!(!current_stage.can_start(&Stages::default(), GameMode::Endless));
+    }
+}
@@ -1,32 +1,31 @@
-use bevy::prelude::*;
+use bevy::{
+    asset::{AssetLoader, BoxedFuture, LoadContext, LoadedAsset},
+    ecs::system::EntityCommands,
+    prelude::*,
+    reflect::TypeUuid,
+    utils::HashMap,
+};
+use serde::Deserialize;
 
-use crate::{components::*, pathfinding::PathFollow};
+use crate::{
+    components::*,
+    effects::{StatusEffects, StatusKind},
+    pathfinding::PathFollow,
+};
 
-#[derive(Bundle, Clone)]
+/// Everything an enemy is spawned with, see `spawn`.
+#[derive(Clone)]
 pub struct UnitBundle {
-    #[bundle]
     sprite: SpriteBundle,
     velocity: Velocity,
     ai_unit: AiUnit,
     path_follow: PathFollow,
     health: Health,
     damage_absorber: DamageAbsorber,
+    armor: Armor,
+    status_effects: StatusEffects,
     gold: Gold,
-    // sprite: SpriteBundle {
-    //     sprite: Sprite {
-    //         color: Color::rgb(1.0, 0.0, 0.0),
-    //         custom_size: Some(Vec2::new(32.0, 32.0)),
-    //         ..Default::default()
-    //     },
-    //     transform: Transform::from_translation(translation.extend(1.0)),
-    //     ..Default::default()
-    // })
-    // .insert(Velocity::new(1.0, 0.0, 0.0))
-    // .insert(AiUnit)
-    // .insert(PathFollow::new(0, 1.5))
-    // .insert(Health::new(1.0))
-    // .insert(DamageAbsorber::new(32.0, 32.0))
-    // .insert(Gold(1));
+    enemy_type: EnemyType,
 }
 impl Default for UnitBundle {
     fn default() -> Self {
@@ -44,98 +43,151 @@ impl Default for UnitBundle {
             path_follow: PathFollow::new(0, 1.5),
             health: Health::new(1.0),
             damage_absorber: DamageAbsorber::new(32.0, 32.0),
+            armor: Armor::default(),
+            status_effects: StatusEffects::new(Color::rgb(1.0, 0.0, 0.5), Vec::new()),
             gold: Gold(1),
+            enemy_type: EnemyType::default(),
         }
     }
 }
 impl UnitBundle {
-    pub fn standard() -> Self {
-        Self {
-            sprite: SpriteBundle {
-                sprite: Sprite {
-                    color: Color::rgb(1.0, 1.0, 1.0),
-                    custom_size: Some(Vec2::new(32.0, 32.0)),
-                    ..Default::default()
-                },
-                ..Default::default()
-            },
-            velocity: Velocity::new(1.0, 0.0, 0.0),
-            path_follow: PathFollow::new(0, 1.5),
-            health: Health::new(1.0),
-            damage_absorber: DamageAbsorber::new(32.0, 32.0),
-            gold: Gold(1),
-            ..Default::default()
-        }
+    pub fn spawn<'w, 's, 'a>(self, commands: &'a mut Commands<'w, 's>) -> EntityCommands<'w, 's, 'a> {
+        let mut unit = commands.spawn_bundle(self.sprite);
+        unit.insert_bundle((
+            self.velocity,
+            self.ai_unit,
+            self.path_follow,
+            self.health,
+            self.damage_absorber,
+            self.armor,
+            self.status_effects,
+            self.gold,
+            self.enemy_type,
+        ));
+        unit
     }
-    pub fn standard_tank() -> Self {
+    pub fn from_archetype(archetype: &EnemyArchetype) -> Self {
         Self {
             sprite: SpriteBundle {
                 sprite: Sprite {
-                    color: Color::rgb(0.0, 0.0, 1.0),
-                    custom_size: Some(Vec2::new(32.0, 32.0)),
+                    color: archetype.color,
+                    custom_size: Some(archetype.size),
                     ..Default::default()
                 },
                 ..Default::default()
             },
-            velocity: Velocity::new(0.0, 0.0, 0.0),
-            path_follow: PathFollow::new(0, 2.0),
-            health: Health::new(2.0),
-            damage_absorber: DamageAbsorber::new(32.0, 32.0),
-            gold: Gold(1),
+            path_follow: PathFollow::new(0, archetype.speed),
+            health: Health::new(archetype.health),
+            damage_absorber: DamageAbsorber::from_vec2(archetype.size),
+            armor: Armor::new(archetype.armor, archetype.resistances),
+            status_effects: StatusEffects::new(archetype.color, archetype.immune.clone()),
+            gold: Gold(archetype.gold),
+            enemy_type: EnemyType(archetype.id.clone()),
             ..Default::default()
         }
     }
-    pub fn standard_fast() -> Self {
+    /// Sends the unit down another of the map's paths.
+    pub fn on_path(self, path: usize) -> Self {
         Self {
-            sprite: SpriteBundle {
-                sprite: Sprite {
-                    color: Color::rgb(1.0, 1.0, 0.0),
-                    custom_size: Some(Vec2::new(32.0, 32.0)),
-                    ..Default::default()
-                },
-                ..Default::default()
-            },
-            velocity: Velocity::new(0.0, 0.0, 0.0),
-            path_follow: PathFollow::new(0, 4.0),
-            health: Health::new(1.0),
-            damage_absorber: DamageAbsorber::new(32.0, 32.0),
-            gold: Gold(1),
-            ..Default::default()
+            path_follow: self.path_follow.on_path(path),
+            ..self
         }
     }
-    pub fn standard_op() -> Self {
+    /// Heads for point `index` of the path instead of the start.
+    pub fn at_point(self, index: usize) -> Self {
         Self {
-            sprite: SpriteBundle {
-                sprite: Sprite {
-                    color: Color::rgb(0.0, 1.0, 1.0),
-                    custom_size: Some(Vec2::new(32.0, 32.0)),
-                    ..Default::default()
-                },
-                ..Default::default()
-            },
-            path_follow: PathFollow::new(0, 8.0),
-            health: Health::new(20.0),
-            damage_absorber: DamageAbsorber::new(32.0, 32.0),
-            gold: Gold(0),
-            ..Default::default()
+            path_follow: self.path_follow.at_point(index),
+            ..self
         }
     }
-    pub fn standard_large() -> Self {
+    pub fn with_health(self, health: f32) -> Self {
         Self {
-            sprite: SpriteBundle {
-                sprite: Sprite {
-                    color: Color::rgb(0.0, 0.5, 1.0),
-                    custom_size: Some(Vec2::new(96.0, 96.0)),
-                    ..Default::default()
-                },
-                ..Default::default()
-            },
-            velocity: Velocity::new(0.0, 0.0, 0.0),
-            path_follow: PathFollow::new(0, 4.0),
-            health: Health::new(100.0),
-            damage_absorber: DamageAbsorber::new(96.0, 96.0),
-            gold: Gold(1),
-            ..Default::default()
+            health: Health::new(health),
+            ..self
         }
     }
-}
\ No newline at end of file
+}
+
+#[derive(Clone, Debug, Component, Default)]
+/// Id of the enemy archetype a unit was built from
+pub struct EnemyType(pub String);
+
+/// Every enemy type, loaded from a `.enemies.ron` file.
+#[derive(Debug, Clone, Deserialize, TypeUuid)]
+#[uuid = "e0ab055d-12b9-4957-b178-4b7a7ea9401a"]
+pub struct EnemyDefinitions {
+    pub enemies: Vec<EnemyArchetype>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct EnemyArchetype {
+    /// Unique key used by wave files and debug keys.
+    pub id: String,
+    pub color: Color,
+    /// Sprite and hitbox size.
+    pub size: Vec2,
+    /// Units per second along the path.
+    pub speed: f32,
+    pub health: f32,
+    /// Paid to the player on death.
+    pub gold: u32,
+    /// Taken off every physical hit.
+    #[serde(default)]
+    pub armor: f32,
+    #[serde(default)]
+    pub resistances: Resistances,
+    /// Status effects that don't work on this enemy.
+    #[serde(default)]
+    pub immune: Vec<StatusKind>,
+}
+
+#[derive(Default)]
+pub struct EnemyDefinitionsLoader;
+
+impl AssetLoader for EnemyDefinitionsLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), anyhow::Error>> {
+        Box::pin(async move {
+            let enemies = ron::de::from_bytes::<EnemyDefinitions>(bytes)?;
+            load_context.set_default_asset(LoadedAsset::new(enemies));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["enemies.ron"]
+    }
+}
+
+/// Handle to the enemy definitions used by the current game
+pub struct EnemyDefinitionsHandle(pub Handle<EnemyDefinitions>);
+
+/// Enemy archetypes by id, rebuilt whenever the enemy file changes
+#[derive(Debug, Default)]
+pub struct EnemyRegistry(HashMap<String, EnemyArchetype>);
+impl EnemyRegistry {
+    pub fn from_definitions(definitions: &EnemyDefinitions) -> Self {
+        Self(
+            definitions
+                .enemies
+                .iter()
+                .map(|enemy| (enemy.id.clone(), enemy.clone()))
+                .collect(),
+        )
+    }
+    pub fn get(&self, id: &str) -> Option<&EnemyArchetype> {
+        self.0.get(id)
+    }
+    pub fn contains(&self, id: &str) -> bool {
+        self.0.contains_key(id)
+    }
+    pub fn build(&self, id: &str) -> Option<UnitBundle> {
+        self.get(id).map(UnitBundle::from_archetype)
+    }
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
@@ -0,0 +1,238 @@
+use bevy::{
+    asset::{AssetLoader, BoxedFuture, LoadContext, LoadedAsset},
+    prelude::*,
+    reflect::TypeUuid,
+};
+use serde::Deserialize;
+use thiserror::Error;
+
+use crate::pathfinding::NavPaths;
+
+use super::unitdata::EnemyRegistry;
+
+/// Stages making up a game, loaded from a `.waves.ron` file.
+#[derive(Debug, Clone, Deserialize, TypeUuid)]
+#[uuid = "f2fca38d-b327-4623-b9db-9dd1763ddb8e"]
+pub struct WaveDefinitions {
+    pub stages: Vec<StageDefinition>,
+    /// Stages to clear to win a campaign, defaults to the number of stages in the file. Past the
+    /// end of `stages`, harder ones are generated with `StageData::scale_with_stage`, as they
+    /// are forever in endless mode.
+    #[serde(default)]
+    pub campaign_length: Option<usize>,
+    /// Seconds between a stage finishing and the next one being sent, with auto-send on.
+    #[serde(default = "WaveDefinitions::default_send_delay")]
+    pub send_delay: f32,
+    /// Gold per second left on the countdown, paid for sending the next stage early.
+    #[serde(default = "WaveDefinitions::default_early_bonus")]
+    pub early_bonus: f32,
+    /// Let the next stage be sent while units from the last one are still on the field.
+    /// Otherwise it has to be cleared first, and the countdown only starts then.
+    #[serde(default)]
+    pub overlap_waves: bool,
+}
+impl WaveDefinitions {
+    pub fn default_send_delay() -> f32 {
+        10.0
+    }
+    pub fn default_early_bonus() -> f32 {
+        2.0
+    }
+    pub fn campaign_length(&self) -> usize {
+        self.campaign_length.unwrap_or(self.stages.len())
+    }
+    /// Checks timings, which can be done as soon as the file is parsed.
+    pub fn validate(&self) -> Result<(), WaveError> {
+        if self.send_delay < 0.0 {
+            return Err(WaveError::InvalidSendDelay(self.send_delay));
+        }
+        if self.stages.is_empty() {
+            return Err(WaveError::NoStages);
+        }
+        if self.campaign_length() == 0 {
+            return Err(WaveError::EmptyCampaign);
+        }
+        for (stage_index, stage) in self.stages.iter().enumerate() {
+            for (group_index, group) in stage.groups.iter().enumerate() {
+                if group.interval <= 0.0 {
+                    return Err(WaveError::InvalidInterval {
+                        stage: stage_index,
+                        group: group_index,
+                        interval: group.interval,
+                    });
+                }
+                if group.delay < 0.0 {
+                    return Err(WaveError::InvalidDelay {
+                        stage: stage_index,
+                        group: group_index,
+                        delay: group.delay,
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+    /// Checks every group spawns an enemy type that exists in `registry`.
+    pub fn validate_enemies(&self, registry: &EnemyRegistry) -> Result<(), WaveError> {
+        for (stage_index, stage) in self.stages.iter().enumerate() {
+            for (group_index, group) in stage.groups.iter().enumerate() {
+                if !registry.contains(&group.enemy) {
+                    return Err(WaveError::UnknownEnemy {
+                        stage: stage_index,
+                        group: group_index,
+                        enemy: group.enemy.clone(),
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+    /// Checks every group with a fixed path names one that exists on the map.
+    pub fn validate_paths(&self, paths: &NavPaths) -> Result<(), WaveError> {
+        for (stage_index, stage) in self.stages.iter().enumerate() {
+            for (group_index, group) in stage.groups.iter().enumerate() {
+                if let Some(path) = &group.path {
+                    if paths.find(path).is_none() {
+                        return Err(WaveError::UnknownPath {
+                            stage: stage_index,
+                            group: group_index,
+                            path: path.clone(),
+                        });
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct StageDefinition {
+    /// Gold paid out when the stage finishes.
+    pub reward: u32,
+    /// Groups are spawned one after another, in order.
+    pub groups: Vec<GroupDefinition>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct GroupDefinition {
+    /// Enemy type to spawn.
+    pub enemy: String,
+    pub count: usize,
+    /// Seconds between spawns.
+    pub interval: f32,
+    /// Seconds to wait before the group starts spawning.
+    #[serde(default)]
+    pub delay: f32,
+    /// Name of the map path to spawn on. Without one, units take turns between every path.
+    #[serde(default)]
+    pub path: Option<String>,
+}
+
+#[derive(Debug, Error)]
+pub enum WaveError {
+    #[error("stage {stage}, group {group}: unknown enemy type \"{enemy}\"")]
+    UnknownEnemy {
+        stage: usize,
+        group: usize,
+        enemy: String,
+    },
+    #[error("stage {stage}, group {group}: the map has no path called \"{path}\"")]
+    UnknownPath {
+        stage: usize,
+        group: usize,
+        path: String,
+    },
+    #[error("stage {stage}, group {group}: spawn interval must be positive, got {interval}")]
+    InvalidInterval {
+        stage: usize,
+        group: usize,
+        interval: f32,
+    },
+    #[error("send_delay can't be negative, got {0}")]
+    InvalidSendDelay(f32),
+    #[error("the wave file has no stages, add at least one")]
+    NoStages,
+    #[error("the campaign has no stages, set campaign_length or add some")]
+    EmptyCampaign,
+    #[error("stage {stage}, group {group}: delay can't be negative, got {delay}")]
+    InvalidDelay {
+        stage: usize,
+        group: usize,
+        delay: f32,
+    },
+}
+
+#[derive(Default)]
+pub struct WaveDefinitionsLoader;
+
+impl AssetLoader for WaveDefinitionsLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), anyhow::Error>> {
+        Box::pin(async move {
+            let waves = ron::de::from_bytes::<WaveDefinitions>(bytes)?;
+            waves.validate()?;
+            load_context.set_default_asset(LoadedAsset::new(waves));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["waves.ron"]
+    }
+}
+
+/// Handle to the wave definitions used by the current game
+pub struct WaveDefinitionsHandle(pub Handle<WaveDefinitions>);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn waves(text: &str) -> WaveDefinitions {
+        ron::from_str(text).unwrap()
+    }
+
+    #[test]
+    fn default_waves_are_valid() {
+        let waves = waves(include_str!("../../assets/data/default.waves.ron"));
+        assert!(waves.validate().is_ok());
+    }
+
+    #[test]
+    fn rejects_bad_timings() {
+        let interval = waves(
+            "(stages: [(reward: 1, groups: [
+                (enemy: \"standard\", count: 1, interval: 0.5),
+                (enemy: \"standard\", count: 1, interval: 0.0),
+            ])])",
+        );
+        assert!(matches!(
+            interval.validate(),
+            Err(WaveError::InvalidInterval { stage: 0, group: 1, .. })
+        ));
+        let delay = waves(
+            "(stages: [(reward: 1, groups: [
+                (enemy: \"standard\", count: 1, interval: 0.5, delay: -1.0),
+            ])])",
+        );
+        assert!(matches!(
+            delay.validate(),
+            Err(WaveError::InvalidDelay { stage: 0, group: 0, .. })
+        ));
+        let send_delay = waves("(stages: [(reward: 1, groups: [])], send_delay: -2.0)");
+        assert!(matches!(send_delay.validate(), Err(WaveError::InvalidSendDelay(_))));
+    }
+
+    #[test]
+    fn rejects_empty_campaigns() {
+        assert!(matches!(waves("(stages: [])").validate(), Err(WaveError::NoStages)));
+        let generated_only = waves("(stages: [], campaign_length: Some(3))");
+        assert!(matches!(generated_only.validate(), Err(WaveError::NoStages)));
+        let endless_only = waves("(stages: [(reward: 1, groups: [])], campaign_length: Some(0))");
+        assert!(matches!(endless_only.validate(), Err(WaveError::EmptyCampaign)));
+    }
+}
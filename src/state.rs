@@ -0,0 +1,69 @@
+use bevy::prelude::*;
+
+use crate::simulation::{GameSpeed, SimulationClock};
+
+/// Drives the app between the menus and the game. Systems that only make sense in one state are
+/// added to that state's `SystemSet`s, and anything spawned for a state is tagged with
+/// `DespawnOnExit` so it goes when the state ends.
+pub struct StatePlugin;
+
+impl Plugin for StatePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_state(AppState::MainMenu)
+            .add_system_set(SystemSet::on_enter(AppState::Playing).with_system(reset_clock))
+            .add_system_set(SystemSet::on_resume(AppState::Playing).with_system(start_clock))
+            .add_system_set(SystemSet::on_pause(AppState::Playing).with_system(stop_clock))
+            .add_system_set(SystemSet::on_exit(AppState::Playing).with_system(stop_clock))
+            .add_system_set(SystemSet::on_enter(AppState::MainMenu).with_system(stop_clock));
+        for state in AppState::ALL {
+            app.add_system_set(SystemSet::on_exit(state).with_system(
+                move |mut commands: Commands, entities: Query<(Entity, &DespawnOnExit)>| {
+                    for (entity, tag) in entities.iter() {
+                        if tag.0 == state {
+                            commands.entity(entity).despawn_recursive();
+                        }
+                    }
+                },
+            ));
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AppState {
+    MainMenu,
+    Playing,
+    /// Pushed on top of `Playing`, so the game is kept underneath
+    Paused,
+    GameOver,
+    /// Every stage of the campaign was cleared
+    Victory,
+}
+impl AppState {
+    const ALL: [Self; 5] = [
+        Self::MainMenu,
+        Self::Playing,
+        Self::Paused,
+        Self::GameOver,
+        Self::Victory,
+    ];
+}
+
+#[derive(Debug, Clone, Copy, Component)]
+/// Despawns the entity, and its children, when the app leaves this state
+pub struct DespawnOnExit(pub AppState);
+
+/// A new game starts from the first tick at normal speed.
+fn reset_clock(mut clock: ResMut<SimulationClock>, mut speed: ResMut<GameSpeed>) {
+    *clock = SimulationClock::default();
+    *speed = GameSpeed::default();
+}
+
+/// The simulation only runs while playing, not in menus or paused.
+fn start_clock(mut clock: ResMut<SimulationClock>) {
+    clock.set_running(true);
+}
+
+fn stop_clock(mut clock: ResMut<SimulationClock>) {
+    clock.set_running(false);
+}
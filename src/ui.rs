@@ -1,16 +1,43 @@
 use bevy::prelude::*;
 
-use crate::{components::{Gold, Lives}, stages::CurrentStage, build::{TowerBundle, BuildIndicator}};
+use crate::{
+    actions::{PlayerAction, PlayerActions},
+    beam::Beam,
+    build::{
+        manage::{SelectedTower, TowerType, UpgradeLevel},
+        towerdata::{LoadedTowers, TowerDefinition, TowerDefinitions, TowerDefinitionsHandle},
+        BuildIndicator,
+    },
+    components::{Aim, BulletGenerator, CursorOverUi, Gold, Lives, Targeting},
+    simulation::GameSpeed,
+    stages::{AutoSend, CurrentStage, GameMode, Stages},
+    state::{AppState, DespawnOnExit},
+};
 
 pub struct UiPlugin;
 
 impl Plugin for UiPlugin {
     fn build(&self, app: &mut App) {
         app
-            .add_startup_system(setup)
-            .add_system(button_system)
-            .add_system(button_start_system)
-            .add_system(update_ui_gold);
+            .add_system_set(SystemSet::on_enter(AppState::Playing).with_system(setup))
+            .add_system_set(
+                SystemSet::on_update(AppState::Playing)
+                    .with_system(spawn_tower_buttons)
+                    .with_system(button_system)
+                    .with_system(button_start_system)
+                    .with_system(button_auto_send_system)
+                    .with_system(update_send_buttons)
+                    .with_system(auto_send_keys)
+                    .with_system(button_upgrade_system)
+                    .with_system(button_sell_system)
+                    .with_system(button_targeting_system)
+                    .with_system(button_speed_system)
+                    .with_system(colour_speed_buttons)
+                    .with_system(speed_keys)
+                    .with_system(update_ui_gold)
+                    .with_system(update_selected_panel)
+                    .with_system(update_cursor_over_ui),
+            );
     }
 }
 
@@ -33,30 +60,228 @@ fn update_ui_gold(
     }
 }
 
-const NORMAL_BUTTON: Color = Color::rgb(0.15, 0.15, 0.15);
-const HOVERED_BUTTON: Color = Color::rgb(0.25, 0.25, 0.25);
-const PRESSED_BUTTON: Color = Color::rgb(0.35, 0.75, 0.35);
+#[derive(Component)]
+/// Panel that blocks mouse clicks from reaching the map
+struct SidePanel;
+
+fn update_cursor_over_ui(
+    windows: Res<Windows>,
+    panels: Query<(&Node, &GlobalTransform), With<SidePanel>>,
+    mut over_ui: ResMut<CursorOverUi>,
+) {
+    let cursor = windows
+        .get_primary()
+        .and_then(|window| window.cursor_position());
+    over_ui.0 = match cursor {
+        Some(cursor) => panels.iter().any(|(node, transform)| {
+            let center = transform.translation.truncate();
+            let extents = node.size / 2.0;
+            (center - extents).cmple(cursor).all() && cursor.cmplt(center + extents).all()
+        }),
+        None => false,
+    };
+}
+
+#[derive(Component)]
+/// Panel describing the selected tower, hidden when nothing is selected
+struct SelectedTowerPanel;
+
+#[derive(Component)]
+struct UpgradeButton;
+
+#[derive(Component)]
+struct SellButton;
+
+#[derive(Component)]
+struct TargetingButton;
+
+/// Which line of the selected tower panel a text shows
+#[derive(Component, Clone, Copy)]
+enum PanelText {
+    /// Name, level and stats
+    Details,
+    Upgrade,
+    Sell,
+    Targeting,
+}
+
+/// Stats shown for the selected tower
+type TowerStats<'w, 's> = Query<
+    'w,
+    's,
+    (
+        &'static TowerType,
+        &'static UpgradeLevel,
+        Option<&'static BulletGenerator>,
+        Option<&'static Beam>,
+        &'static Aim,
+        &'static Targeting,
+    ),
+>;
+
+fn update_selected_panel(
+    selected: Res<SelectedTower>,
+    towers: TowerStats,
+    definitions: LoadedTowers,
+    mut panel: Query<&mut Style, With<SelectedTowerPanel>>,
+    mut texts: Query<(&mut Text, &PanelText)>,
+) {
+    let definitions = definitions.get();
+    let tower = selected
+        .0
+        .and_then(|entity| towers.get(entity).ok())
+        .and_then(|(tower_type, level, generator, beam, aim, targeting)| {
+            definitions
+                .and_then(|definitions| definitions.get(&tower_type.0))
+                .map(|definition| (definition, level, generator, beam, aim, targeting))
+        });
+    for mut style in panel.iter_mut() {
+        style.display = if tower.is_some() {
+            Display::Flex
+        } else {
+            Display::None
+        };
+    }
+    if let Some((definition, level, generator, beam, aim, targeting)) = tower {
+        let refund = definitions.map_or(0, |definitions| definitions.refund(&definition.id, level.0));
+        let damage = match (generator, beam) {
+            (Some(generator), _) => format!(
+                "{} ({})",
+                generator.bullet_damage,
+                generator.bullet_damage_type.name()
+            ),
+            (None, Some(beam)) => format!("{}/s ({})", beam.dps, beam.damage_type.name()),
+            (None, None) => "none".to_string(),
+        };
+        for (mut text, line) in texts.iter_mut() {
+            text.sections[0].value = match line {
+                PanelText::Details => format!(
+                    "{}\nLevel {}/{}\nDamage: {}\nRange: {}",
+                    definition.name,
+                    level.0 + 1,
+                    definition.upgrades.len() + 1,
+                    damage,
+                    aim.radius,
+                ),
+                PanelText::Upgrade => match definition.upgrades.get(level.0) {
+                    Some(tier) => format!("Upgrade (${})", tier.cost),
+                    None => "Fully upgraded".to_string(),
+                },
+                PanelText::Sell => format!("Sell (${})", refund),
+                PanelText::Targeting => format!("Target: {}", targeting.name()),
+            };
+        }
+    }
+}
+
+/// Buttons whose interaction changed this frame, with their `C` component
+pub type ButtonInteractions<'w, 's, C> = Query<
+    'w,
+    's,
+    (&'static Interaction, &'static mut UiColor, &'static C),
+    (Changed<Interaction>, With<Button>),
+>;
+
+pub const NORMAL_BUTTON: Color = Color::rgb(0.15, 0.15, 0.15);
+pub const HOVERED_BUTTON: Color = Color::rgb(0.25, 0.25, 0.25);
+pub const PRESSED_BUTTON: Color = Color::rgb(0.35, 0.75, 0.35);
 
 #[derive(Component)]
 struct StartButton;
 
+#[derive(Component)]
+struct StartButtonText;
+
+#[derive(Component)]
+struct AutoSendButton;
+
+#[derive(Component)]
+struct AutoSendButtonText;
+
 #[derive(Component)]
 struct TowerButton {
-    tower_bundle: TowerBundle,
+    tower: TowerDefinition,
 }
 impl TowerButton {
-    fn new(tower_bundle: TowerBundle) -> Self {
+    fn new(tower: TowerDefinition) -> Self {
         Self {
-            tower_bundle
+            tower
+        }
+    }
+}
+
+#[derive(Component, Clone, Copy)]
+/// Pauses the game, or unpauses it at one of `GameSpeed::SPEEDS`
+enum SpeedButton {
+    Pause,
+    Speed(f64),
+}
+impl SpeedButton {
+    fn label(self) -> String {
+        match self {
+            SpeedButton::Pause => "||".to_string(),
+            SpeedButton::Speed(multiplier) => format!("{}x", multiplier),
+        }
+    }
+    fn is_active(self, speed: &GameSpeed) -> bool {
+        match self {
+            SpeedButton::Pause => speed.paused,
+            SpeedButton::Speed(multiplier) => !speed.paused && speed.multiplier == multiplier,
+        }
+    }
+    fn press(self, speed: &mut GameSpeed) {
+        match self {
+            SpeedButton::Pause => speed.paused = !speed.paused,
+            SpeedButton::Speed(multiplier) => speed.set(multiplier),
+        }
+    }
+}
+
+#[derive(Component)]
+/// Node holding one button per tower definition
+struct TowerButtonList;
+
+/// Rebuilds the tower buttons whenever the tower definitions are loaded or changed, and fills
+/// the list when the UI is spawned after they have already loaded.
+fn spawn_tower_buttons(
+    mut commands: Commands,
+    mut events: EventReader<AssetEvent<TowerDefinitions>>,
+    definitions: Res<Assets<TowerDefinitions>>,
+    handle: Option<Res<TowerDefinitionsHandle>>,
+    list: Query<Entity, With<TowerButtonList>>,
+    added: Query<(), Added<TowerButtonList>>,
+    asset_server: Res<AssetServer>,
+) {
+    let mut changed = None;
+    for event in events.iter() {
+        match event {
+            AssetEvent::Created { handle } | AssetEvent::Modified { handle } => changed = Some(handle.clone()),
+            AssetEvent::Removed { .. } => continue,
         }
     }
+    if changed.is_none() && !added.is_empty() {
+        changed = handle.map(|handle| handle.0.clone());
+    }
+    let definitions = match changed.and_then(|handle| definitions.get(handle)) {
+        Some(definitions) => definitions,
+        None => return,
+    };
+    let font: Handle<Font> = asset_server.load("fonts/NotoSans-Regular.ttf");
+    for list in list.iter() {
+        commands.entity(list).despawn_descendants();
+        commands.entity(list).with_children(|parent| {
+            for tower in definitions.towers.iter() {
+                parent.spawn_bundle(tower_button(tower.clone()))
+                .with_children(|parent| {
+                    parent.spawn_bundle(tower_text(&tower.label(), font.clone()));
+                });
+            }
+        });
+    }
 }
 
 fn button_system(
-    mut interaction_query: Query<
-        (&Interaction, &mut UiColor, &TowerButton),
-        (Changed<Interaction>, With<Button>),
-    >,
+    mut interaction_query: ButtonInteractions<TowerButton>,
     mut indicator: Query<&mut BuildIndicator>,
 ) {
     for (interaction, mut color, tower) in interaction_query.iter_mut() {
@@ -64,7 +289,7 @@ fn button_system(
             Interaction::Clicked => {
                 *color = PRESSED_BUTTON.into();
                 for mut indicator in indicator.iter_mut() {
-                    indicator.tower = tower.tower_bundle.clone();
+                    indicator.tower = Some(tower.tower.clone());
                 }
             }
             Interaction::Hovered => {
@@ -77,18 +302,14 @@ fn button_system(
     }
 }
 fn button_start_system(
-    mut interaction_query: Query<
-        (&Interaction, &mut UiColor),
-        (Changed<Interaction>, With<Button>, With<StartButton>),
-    >,
-    mut stages: ResMut<CurrentStage>,
+    mut interaction_query: ButtonInteractions<StartButton>,
+    mut actions: ResMut<PlayerActions>,
 ) {
-    for (interaction, mut color) in interaction_query.iter_mut() {
+    for (interaction, mut color, _) in interaction_query.iter_mut() {
         match *interaction {
             Interaction::Clicked => {
                 *color = Color::rgb(0.5, 1.0, 0.5).into();
-                stages.start_stage();
-                info!("Starting stage {}...", stages.index);
+                actions.push(PlayerAction::StartStage);
             }
             Interaction::Hovered => {
                 *color = Color::rgb(0.4, 0.8, 0.4).into();
@@ -100,46 +321,257 @@ fn button_start_system(
     }
 }
 
+fn button_auto_send_system(
+    mut interaction_query: ButtonInteractions<AutoSendButton>,
+    mut actions: ResMut<PlayerActions>,
+) {
+    for (interaction, mut color, _) in interaction_query.iter_mut() {
+        match *interaction {
+            Interaction::Clicked => {
+                *color = PRESSED_BUTTON.into();
+                actions.push(PlayerAction::ToggleAutoSend);
+            }
+            Interaction::Hovered => {
+                *color = HOVERED_BUTTON.into();
+            }
+            Interaction::None => {
+                *color = NORMAL_BUTTON.into();
+            }
+        }
+    }
+}
+
+/// Shows the countdown and early bonus on the send button, and whether auto-send is on.
+fn update_send_buttons(
+    current_stage: Res<CurrentStage>,
+    stages: Res<Stages>,
+    mode: Res<GameMode>,
+    auto_send: Res<AutoSend>,
+    mut start_text: Query<&mut Text, With<StartButtonText>>,
+    mut auto_send_text: Query<&mut Text, (With<AutoSendButtonText>, Without<StartButtonText>)>,
+) {
+    let bonus = current_stage.early_bonus(&stages, &auto_send);
+    let start = match current_stage.countdown {
+        _ if !stages.has_stage(current_stage.index, *mode) => "No Waves Left".to_string(),
+        Some(countdown) if auto_send.0 && !current_stage.in_stage => {
+            format!("Send Wave ({:.0}s, +{})", countdown.ceil(), bonus)
+        }
+        _ => "Send Wave".to_string(),
+    };
+    for mut text in start_text.iter_mut() {
+        text.sections[0].value = start.clone();
+    }
+    for mut text in auto_send_text.iter_mut() {
+        text.sections[0].value = format!("Auto Send: {}", if auto_send.0 { "On" } else { "Off" });
+    }
+}
+
+/// A toggles auto-send.
+fn auto_send_keys(input: Res<Input<KeyCode>>, mut actions: ResMut<PlayerActions>) {
+    if input.just_pressed(KeyCode::A) {
+        actions.push(PlayerAction::ToggleAutoSend);
+    }
+}
+
+fn button_upgrade_system(
+    mut interaction_query: ButtonInteractions<UpgradeButton>,
+    selected: Res<SelectedTower>,
+    towers: Query<&Transform, With<TowerType>>,
+    mut actions: ResMut<PlayerActions>,
+) {
+    for (interaction, mut color, _) in interaction_query.iter_mut() {
+        match *interaction {
+            Interaction::Clicked => {
+                *color = PRESSED_BUTTON.into();
+                if let Some(transform) = selected.0.and_then(|tower| towers.get(tower).ok()) {
+                    actions.push(PlayerAction::UpgradeTower {
+                        position: transform.translation.truncate(),
+                    });
+                }
+            }
+            Interaction::Hovered => {
+                *color = HOVERED_BUTTON.into();
+            }
+            Interaction::None => {
+                *color = NORMAL_BUTTON.into();
+            }
+        }
+    }
+}
+
+fn button_sell_system(
+    mut interaction_query: ButtonInteractions<SellButton>,
+    selected: Res<SelectedTower>,
+    towers: Query<&Transform, With<TowerType>>,
+    mut actions: ResMut<PlayerActions>,
+) {
+    for (interaction, mut color, _) in interaction_query.iter_mut() {
+        match *interaction {
+            Interaction::Clicked => {
+                *color = PRESSED_BUTTON.into();
+                if let Some(transform) = selected.0.and_then(|tower| towers.get(tower).ok()) {
+                    actions.push(PlayerAction::SellTower {
+                        position: transform.translation.truncate(),
+                    });
+                }
+            }
+            Interaction::Hovered => {
+                *color = HOVERED_BUTTON.into();
+            }
+            Interaction::None => {
+                *color = NORMAL_BUTTON.into();
+            }
+        }
+    }
+}
+
+fn button_targeting_system(
+    mut interaction_query: ButtonInteractions<TargetingButton>,
+    selected: Res<SelectedTower>,
+    towers: Query<(&Transform, &Targeting)>,
+    mut actions: ResMut<PlayerActions>,
+) {
+    for (interaction, mut color, _) in interaction_query.iter_mut() {
+        match *interaction {
+            Interaction::Clicked => {
+                *color = PRESSED_BUTTON.into();
+                if let Some((transform, mode)) = selected.0.and_then(|tower| towers.get(tower).ok()) {
+                    actions.push(PlayerAction::SetTargeting {
+                        position: transform.translation.truncate(),
+                        targeting: mode.next(),
+                    });
+                }
+            }
+            Interaction::Hovered => {
+                *color = HOVERED_BUTTON.into();
+            }
+            Interaction::None => {
+                *color = NORMAL_BUTTON.into();
+            }
+        }
+    }
+}
+
+fn button_speed_system(
+    mut interaction_query: ButtonInteractions<SpeedButton>,
+    mut speed: ResMut<GameSpeed>,
+) {
+    for (interaction, _, button) in interaction_query.iter_mut() {
+        if *interaction == Interaction::Clicked {
+            button.press(&mut speed);
+        }
+    }
+}
+
+/// Highlights the button for the current speed, which can also change from the keyboard.
+fn colour_speed_buttons(
+    mut buttons: Query<(&Interaction, &mut UiColor, &SpeedButton), With<Button>>,
+    speed: Res<GameSpeed>,
+) {
+    for (interaction, mut color, button) in buttons.iter_mut() {
+        *color = if button.is_active(&speed) {
+            PRESSED_BUTTON.into()
+        } else if *interaction == Interaction::Hovered {
+            HOVERED_BUTTON.into()
+        } else {
+            NORMAL_BUTTON.into()
+        };
+    }
+}
+
+/// P pauses and unpauses, 1, 2 and 4 pick a speed.
+fn speed_keys(input: Res<Input<KeyCode>>, mut speed: ResMut<GameSpeed>) {
+    if input.just_pressed(KeyCode::P) {
+        SpeedButton::Pause.press(&mut speed);
+    }
+    let keys = [KeyCode::Key1, KeyCode::Key2, KeyCode::Key4];
+    for (key, multiplier) in keys.iter().zip(GameSpeed::SPEEDS) {
+        if input.just_pressed(*key) {
+            speed.set(multiplier);
+        }
+    }
+}
+
 fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
-    // ui camera
-    commands.spawn_bundle(UiCameraBundle::default());
     let font = asset_server.load("fonts/NotoSans-Regular.ttf");
     //commands.spawn_bundle(UiImage(asset_server.load(circle.png)));
     commands
         .spawn_bundle(screen_fill_node())
+        .insert(DespawnOnExit(AppState::Playing))
         .with_children(|parent| {
-            // left vertical fill (border)
             parent
-                .spawn_bundle(left_fill(Val::Auto))
+                .spawn_bundle(side_column(JustifyContent::SpaceBetween))
                 .with_children(|parent| {
-                    parent.spawn_bundle(tower_button(TowerBundle::dart(&asset_server)))
-                    .with_children(|parent| {
-                        parent.spawn_bundle(tower_text("Dart Tower ($100)", font.clone()));
-                    });
-                    parent.spawn_bundle(tower_button(TowerBundle::big(&asset_server)))
-                    .with_children(|parent| {
-                        parent.spawn_bundle(tower_text("Big Tower ($200)", font.clone()));
-                    });
-                    parent.spawn_bundle(tower_button(TowerBundle::fast()))
-                    .with_children(|parent| {
-                        parent.spawn_bundle(tower_text("Fast Tower ($800)", font.clone()));
-                    });
-                    parent.spawn_bundle(tower_button(TowerBundle::strong()))
-                    .with_children(|parent| {
-                        parent.spawn_bundle(tower_text("Strong Tower ($2000)", font.clone()));
-                    });
-                    parent.spawn_bundle(start_button())
-                    .with_children(|parent| {
-                        parent.spawn_bundle(tower_text("Send Wave", font.clone()));
-                    });
+                    // left vertical fill (border)
+                    parent
+                        .spawn_bundle(left_fill(Val::Auto))
+                        .insert(SidePanel)
+                        .with_children(|parent| {
+                            parent.spawn_bundle(tower_list())
+                            .insert(TowerButtonList);
+                            parent.spawn_bundle(start_button())
+                            .insert(StartButton)
+                            .with_children(|parent| {
+                                parent.spawn_bundle(tower_text("Send Wave", font.clone()))
+                                .insert(StartButtonText);
+                            });
+                            parent.spawn_bundle(action_button())
+                            .insert(AutoSendButton)
+                            .with_children(|parent| {
+                                parent.spawn_bundle(tower_text("Auto Send: Off", font.clone()))
+                                .insert(AutoSendButtonText);
+                            });
+                            parent.spawn_bundle(button_row())
+                            .with_children(|parent| {
+                                let speeds = GameSpeed::SPEEDS.into_iter().map(SpeedButton::Speed);
+                                for button in std::iter::once(SpeedButton::Pause).chain(speeds) {
+                                    parent.spawn_bundle(small_button())
+                                    .insert(button)
+                                    .with_children(|parent| {
+                                        parent.spawn_bundle(tower_text(&button.label(), font.clone()));
+                                    });
+                                }
+                            });
+                        });
+                    parent
+                        .spawn_bundle(left_fill(Val::Px(130.0)))
+                        .insert(SidePanel)
+                        .with_children(|parent| {
+                            parent.spawn_bundle(resource_text(font.clone()))
+                            .insert(ResourceText);
+                        }
+                    );
                 });
             parent
-                .spawn_bundle(left_fill(Val::Px(130.0)))
+                .spawn_bundle(side_column(JustifyContent::FlexEnd))
                 .with_children(|parent| {
-                    parent.spawn_bundle(resource_text(font.clone()))
-                    .insert(ResourceText);
-                }
-            );
+                    parent
+                        .spawn_bundle(left_fill(Val::Auto))
+                        .insert(SidePanel)
+                        .insert(SelectedTowerPanel)
+                        .with_children(|parent| {
+                            parent.spawn_bundle(tower_text("", font.clone()))
+                            .insert(PanelText::Details);
+                            parent.spawn_bundle(action_button())
+                            .insert(UpgradeButton)
+                            .with_children(|parent| {
+                                parent.spawn_bundle(tower_text("Upgrade", font.clone()))
+                                .insert(PanelText::Upgrade);
+                            });
+                            parent.spawn_bundle(action_button())
+                            .insert(TargetingButton)
+                            .with_children(|parent| {
+                                parent.spawn_bundle(tower_text("Target", font.clone()))
+                                .insert(PanelText::Targeting);
+                            });
+                            parent.spawn_bundle(action_button())
+                            .insert(SellButton)
+                            .with_children(|parent| {
+                                parent.spawn_bundle(tower_text("Sell", font.clone()))
+                                .insert(PanelText::Sell);
+                            });
+                        });
+                });
             }
         );
     // commands
@@ -170,7 +602,6 @@ fn left_fill(height: Val) -> NodeBundle {
                 bottom: Val::Px(5.0),
                 left: Val::Px(5.0),
                 right: Val::Px(5.0),
-                ..Default::default()
             },
             justify_content: JustifyContent::FlexEnd,
             flex_direction: FlexDirection::ColumnReverse,
@@ -181,11 +612,36 @@ fn left_fill(height: Val) -> NodeBundle {
     }
 }
 
+fn tower_list() -> NodeBundle {
+    NodeBundle {
+        style: Style {
+            flex_direction: FlexDirection::ColumnReverse,
+            ..Default::default()
+        },
+        color: Color::NONE.into(),
+        ..Default::default()
+    }
+}
+
 pub fn screen_fill_node() -> NodeBundle {
     NodeBundle {
         style: Style {
             size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
             justify_content: JustifyContent::SpaceBetween,
+            flex_direction: FlexDirection::Row,
+            ..Default::default()
+        },
+        color: Color::NONE.into(),
+        ..Default::default()
+    }
+}
+
+/// Full height column down one side of the screen
+fn side_column(justify_content: JustifyContent) -> NodeBundle {
+    NodeBundle {
+        style: Style {
+            size: Size::new(Val::Px(200.0), Val::Percent(100.0)),
+            justify_content,
             flex_direction: FlexDirection::Column,
             ..Default::default()
         },
@@ -226,14 +682,7 @@ struct TowerButtonBundle {
     tower_button: TowerButton,
 }
 
-#[derive(Bundle)]
-struct StartButtonBundle {
-    #[bundle]
-    button_bundle: ButtonBundle,
-    start_button: StartButton,
-}
-
-fn tower_button(tower_bundle: TowerBundle) -> TowerButtonBundle {
+fn tower_button(tower: TowerDefinition) -> TowerButtonBundle {
     TowerButtonBundle {
         button_bundle: ButtonBundle {
             style: Style {
@@ -254,35 +703,90 @@ fn tower_button(tower_bundle: TowerBundle) -> TowerButtonBundle {
             color: NORMAL_BUTTON.into(),
             ..Default::default()
         },
-        tower_button: TowerButton::new(tower_bundle),
+        tower_button: TowerButton::new(tower),
     }
 }
-fn start_button() -> StartButtonBundle {
-    StartButtonBundle {
-        button_bundle: ButtonBundle {
-            style: Style {
-                size: Size::new(Val::Auto, Val::Px(50.0)),
-                // center button
-                margin: Rect {
-                    top: Val::Px(15.0),
-                    bottom: Val::Px(5.0),
-                    left: Val::Px(0.0),
-                    right: Val::Px(0.0),
-                },
-                // horizontally center child text
-                justify_content: JustifyContent::Center,
-                // vertically center child text
-                align_items: AlignItems::Center,
+fn start_button() -> ButtonBundle {
+    ButtonBundle {
+        style: Style {
+            size: Size::new(Val::Auto, Val::Px(50.0)),
+            // center button
+            margin: Rect {
+                top: Val::Px(15.0),
+                bottom: Val::Px(5.0),
+                left: Val::Px(0.0),
+                right: Val::Px(0.0),
+            },
+            // horizontally center child text
+            justify_content: JustifyContent::Center,
+            // vertically center child text
+            align_items: AlignItems::Center,
+            ..Default::default()
+        },
+        color: NORMAL_BUTTON.into(),
+        ..Default::default()
+    }
+}
+
+pub fn action_button() -> ButtonBundle {
+    ButtonBundle {
+        style: Style {
+            size: Size::new(Val::Auto, Val::Px(30.0)),
+            margin: Rect {
+                top: Val::Px(5.0),
+                bottom: Val::Px(5.0),
+                left: Val::Px(0.0),
+                right: Val::Px(0.0),
+            },
+            // horizontally center child text
+            justify_content: JustifyContent::Center,
+            // vertically center child text
+            align_items: AlignItems::Center,
+            ..Default::default()
+        },
+        color: NORMAL_BUTTON.into(),
+        ..Default::default()
+    }
+}
+
+fn button_row() -> NodeBundle {
+    NodeBundle {
+        style: Style {
+            flex_direction: FlexDirection::Row,
+            margin: Rect {
+                top: Val::Px(5.0),
                 ..Default::default()
             },
-            color: NORMAL_BUTTON.into(),
             ..Default::default()
         },
-        start_button: StartButton,
+        color: Color::NONE.into(),
+        ..Default::default()
+    }
+}
+
+/// Button that shares a row with others
+fn small_button() -> ButtonBundle {
+    ButtonBundle {
+        style: Style {
+            size: Size::new(Val::Auto, Val::Px(30.0)),
+            flex_grow: 1.0,
+            margin: Rect {
+                left: Val::Px(2.0),
+                right: Val::Px(2.0),
+                ..Default::default()
+            },
+            // horizontally center child text
+            justify_content: JustifyContent::Center,
+            // vertically center child text
+            align_items: AlignItems::Center,
+            ..Default::default()
+        },
+        color: NORMAL_BUTTON.into(),
+        ..Default::default()
     }
 }
 
-fn tower_text(name: &str, font: Handle<Font>) -> TextBundle {
+pub fn tower_text(name: &str, font: Handle<Font>) -> TextBundle {
     TextBundle {
         text: Text::with_section(
             name,